@@ -1,49 +1,121 @@
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+
 use anyhow::Context as _;
 use clap::Parser;
-use wit2wadm::{cli::Args, wit2wadm_from_component, wit2wadm_from_folder};
+use wit2wadm::catalog::{catalog_table, describe_package};
+use wit2wadm::cli::{Args, CatalogCommand, CheckArgs, Cli, Command, ExplainFormat, LockArgs};
+use wit2wadm::generate::Io;
+use wit2wadm::lockfile::{Lockfile, LOCKFILE_NAME};
+use wit2wadm::render::write_atomic;
+use wit_parser::Resolve;
 
 fn main() {
-    let args = Args::parse();
-
-    let name = args.app_name.unwrap_or_else(|| "wit2wadm".to_string());
-    let description = args
-        .app_description
-        .unwrap_or_else(|| "A wasmCloud Application".to_string());
-    let version = args.app_version.unwrap_or_else(|| "v0.1.0".to_string());
-
-    let manifest = if args.wit_folder_or_component.is_dir() {
-        let world_name = args
-            .world_name
-            .expect("world name is required when providing a WIT folder");
-        wit2wadm_from_folder(
-            &args.wit_folder_or_component,
-            world_name,
-            &name,
-            description,
-            &version,
-            args.app_image
-                .unwrap_or_else(|| format!("myregistry.io/{name}:{version}")),
-        )
-    } else {
-        wit2wadm_from_component(
-            &args.wit_folder_or_component,
-            name,
-            description,
-            version,
-            args.app_image.unwrap_or_else(|| {
-                format!(
-                    "file://./{}",
-                    args.wit_folder_or_component.to_string_lossy()
-                )
-            }),
-        )
-    }
-    .context("failed to convert WIT to WADM")
-    .expect("should be able to convert WIT to WADM");
-
-    let yaml_result = serde_yaml::to_string(&manifest);
-    match yaml_result {
-        Ok(yaml_string) => println!("{}", yaml_string),
-        Err(err) => eprintln!("Error serializing to YAML: {}", err),
+    match Cli::parse().into_command() {
+        Command::Generate(args) => generate(args, None),
+        Command::Inspect(mut args) => {
+            args.explain = args.explain.or(Some(ExplainFormat::Table));
+            generate(args, None)
+        }
+        Command::Check(CheckArgs {
+            manifest,
+            generate: args,
+        }) => generate(args, Some(manifest)),
+        Command::Catalog(CatalogCommand::List) => println!("{}", catalog_table()),
+        Command::Catalog(CatalogCommand::Show { package }) => match describe_package(&package) {
+            Some(description) => println!("{description}"),
+            None => {
+                eprintln!("The catalog has no providers for {package}");
+                std::process::exit(1);
+            }
+        },
+        Command::Lock(args) => {
+            if let Err(err) = lock(args) {
+                eprintln!("{err:?}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Adds or updates the lockfile entries for providers stored as OCI image layouts
+fn lock(args: LockArgs) -> anyhow::Result<()> {
+    let lockfile_path = args
+        .lockfile
+        .unwrap_or_else(|| PathBuf::from(LOCKFILE_NAME));
+    let mut lockfile = Lockfile::load_or_default(&lockfile_path)
+        .with_context(|| format!("failed to load {}", lockfile_path.display()))?;
+    for layout in &args.layouts {
+        let (reference, digest) = lockfile
+            .lock_from_layout(layout)
+            .with_context(|| format!("failed to lock {}", layout.display()))?;
+        eprintln!("Locked {reference} to {digest}");
+    }
+    lockfile
+        .save(&lockfile_path)
+        .with_context(|| format!("failed to save {}", lockfile_path.display()))
+}
+
+/// Generates a manifest, or checks an existing manifest against what would be generated
+fn generate(args: Args, check: Option<PathBuf>) {
+    if let Err(err) = wit2wadm::generate(args, check, &mut StdIo) {
+        eprintln!("{err:?}");
+        std::process::exit(1);
+    }
+}
+
+/// Reads and writes the local filesystem, stdin and stdout
+struct StdIo;
+
+impl Io for StdIo {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        Ok(std::fs::read(path)?)
+    }
+
+    fn read_dir(&self, path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let entries = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        Ok(entries)
+    }
+
+    fn read_stdin(&mut self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_wit(&self, path: &Path) -> anyhow::Result<Resolve> {
+        let mut resolve = Resolve::new();
+        resolve.push_path(path)?;
+        Ok(resolve)
+    }
+
+    fn current_dir(&self) -> anyhow::Result<PathBuf> {
+        Ok(PathBuf::new())
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> anyhow::Result<()> {
+        Ok(std::fs::create_dir_all(path)?)
+    }
+
+    fn write(&mut self, path: &Path, contents: &[u8], overwrite: bool) -> anyhow::Result<()> {
+        write_atomic(path, contents, overwrite)
+    }
+
+    fn print(&mut self, output: &str) {
+        println!("{output}");
+    }
+
+    fn log(&mut self, message: &str) {
+        eprintln!("{message}");
     }
 }
@@ -0,0 +1,37 @@
+//! Compares the metadata-only component scan against a full `wit_component::decode`.
+//!
+//! By default this benchmarks a small generated component. Set `WIT2WADM_BENCH_COMPONENT` to the
+//! path of a real component (e.g. a Python or JavaScript component) to see the difference on
+//! large binaries:
+//!
+//! ```bash
+//! WIT2WADM_BENCH_COMPONENT=./build/app_s.wasm cargo bench -p wit2wadm
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[path = "../src/test_util.rs"]
+mod test_util;
+
+fn bench_component() -> Vec<u8> {
+    if let Ok(path) = std::env::var("WIT2WADM_BENCH_COMPONENT") {
+        return std::fs::read(path).expect("should be able to read benchmark component");
+    }
+    test_util::component()
+}
+
+fn component_interfaces(c: &mut Criterion) {
+    let component = bench_component();
+
+    let mut group = c.benchmark_group("component_interfaces");
+    group.bench_function("scan_component", |b| {
+        b.iter(|| wit2wadm::scan::scan_component(black_box(&component)).unwrap())
+    });
+    group.bench_function("wit_component_decode", |b| {
+        b.iter(|| wit_component::decode(black_box(&component)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, component_interfaces);
+criterion_main!(benches);
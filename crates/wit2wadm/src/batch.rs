@@ -0,0 +1,194 @@
+//! Converting many components at once, each into its own application named after its file and
+//! versioned from its embedded metadata. Finding and reading the components, and spreading the
+//! work over threads, is left to [generate](crate::generate) so it goes through its I/O.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use wadm::model::Manifest;
+use wasmparser::{Chunk, Parser, Payload};
+
+use crate::oci::{strip_digest, ComponentArtifact};
+use crate::plan::ApplicationPlan;
+use crate::{plan_component, plan_to_wadm, GenerateOptions};
+
+/// The custom section tools like `wasm-tools metadata add` record a component's version in
+const VERSION_SECTION: &str = "version";
+
+/// The outcome of converting one component of a batch
+pub struct BatchResult {
+    /// The path the component was read from
+    pub path: PathBuf,
+    /// The plan and manifest of the component's application, or why it couldn't be converted
+    pub result: anyhow::Result<(ApplicationPlan, Manifest)>,
+}
+
+/// Converts a component read from `path` into its own application. The application is named
+/// after the file and versioned from the component's embedded metadata or the tag of its OCI
+/// reference, falling back to `default_version`
+pub fn convert_artifact(
+    path: &Path,
+    artifact: ComponentArtifact,
+    description: &str,
+    default_version: &str,
+    options: &GenerateOptions,
+) -> anyhow::Result<(ApplicationPlan, Manifest)> {
+    let name = application_name(path)?;
+    let version = embedded_version(&artifact.wasm)
+        .or_else(|| artifact.image.as_deref().and_then(image_tag))
+        .unwrap_or_else(|| default_version.to_string());
+    let image = artifact
+        .image
+        .unwrap_or_else(|| format!("file://./{}", path.to_string_lossy()));
+
+    let plan = plan_component(&artifact.wasm, &name, image, options)?;
+    let manifest = plan_to_wadm(&plan, description, &version);
+    Ok((plan, manifest))
+}
+
+/// The name of the application for a component file, e.g. `echo` for `echo.wasm` or
+/// `echo.tar.gz`
+fn application_name(path: &Path) -> anyhow::Result<String> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} has no file name", path.display()))?
+        .to_string_lossy();
+    let name = [".wasm", ".tar.gz", ".tgz", ".tar"]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension))
+        .unwrap_or(&file_name);
+    Ok(name.to_string())
+}
+
+/// The name of a component composed into an application: the repository of the image it was
+/// published as, e.g. `api` for `ghcr.io/acme/api:0.1.0`, or otherwise its file name without
+/// extensions
+pub fn component_name(path: &Path, image: Option<&str>) -> anyhow::Result<String> {
+    let repository = image
+        .filter(|image| !image.starts_with("file://"))
+        .map(|image| {
+            let image = strip_digest(image);
+            let (_, last) = image.rsplit_once('/').unwrap_or(("", image));
+            last.split_once(':').map_or(last, |(name, _)| name)
+        })
+        .filter(|name| !name.is_empty());
+    match repository {
+        Some(name) => Ok(name.to_string()),
+        None => application_name(path),
+    }
+}
+
+/// Reads the version recorded in a component's `version` custom section, if it has one. Only
+/// the outer component's sections are read, nested modules and components are skipped
+fn embedded_version(wasm: &[u8]) -> Option<String> {
+    let mut parser = Parser::new(0);
+    let mut offset = 0;
+    loop {
+        let payload = match parser.parse(&wasm[offset..], true).ok()? {
+            Chunk::Parsed { consumed, payload } => {
+                offset += consumed;
+                payload
+            }
+            Chunk::NeedMoreData(_) => return None,
+        };
+        match payload {
+            Payload::CustomSection(reader) if reader.name() == VERSION_SECTION => {
+                return std::str::from_utf8(reader.data())
+                    .ok()
+                    .map(|version| version.trim().to_string())
+                    .filter(|version| !version.is_empty());
+            }
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => {
+                if let Some((_, range)) = payload.as_section() {
+                    offset = range.end;
+                }
+            }
+            Payload::End(_) => return None,
+            _ => {}
+        }
+    }
+}
+
+/// The tag of an image reference, e.g. `0.1.0` for `ghcr.io/acme/echo:0.1.0@sha256:...`
+fn image_tag(image: &str) -> Option<String> {
+    let reference = strip_digest(image);
+    let (repository, tag) = reference.rsplit_once(':')?;
+    // A colon before the last slash belongs to a registry port, not a tag
+    (!tag.contains('/') && !repository.is_empty()).then(|| tag.to_string())
+}
+
+/// Whether a file is named like a component, i.e. ends in `.wasm`
+pub(crate) fn is_component_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "wasm")
+}
+
+/// Matches a file name against a pattern where `*` matches any number of characters
+pub(crate) fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all, so the whole name has to match
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{application_name, component_name, image_tag, matches_pattern};
+
+    #[test]
+    fn test_batch_names() {
+        assert!(matches_pattern("*.wasm", "echo.wasm"));
+        assert!(matches_pattern("*_s.wasm", "echo_s.wasm"));
+        assert!(matches_pattern("http-*-rust*", "http-hello-rust.wasm"));
+        assert!(!matches_pattern("*_s.wasm", "echo.wasm"));
+        assert!(!matches_pattern("echo*.wasm", "hello.wasm"));
+
+        assert_eq!(
+            application_name(Path::new("build/echo.wasm")).unwrap(),
+            "echo"
+        );
+        assert_eq!(application_name(Path::new("echo.tar.gz")).unwrap(), "echo");
+        assert_eq!(
+            application_name(Path::new("artifacts/echo")).unwrap(),
+            "echo"
+        );
+
+        assert_eq!(
+            image_tag("ghcr.io/acme/echo:0.1.0@sha256:abc").as_deref(),
+            Some("0.1.0")
+        );
+        assert_eq!(image_tag("localhost:5000/acme/echo"), None);
+        assert_eq!(image_tag("file://./echo.wasm"), None);
+
+        let name =
+            |path: &str, image: Option<&str>| component_name(Path::new(path), image).unwrap();
+        assert_eq!(name("build/api.tar.gz", None), "api");
+        assert_eq!(
+            name("build/api.wasm", Some("file://./build/api.wasm")),
+            "api"
+        );
+        assert_eq!(
+            name(
+                "build/api-layout",
+                Some("ghcr.io/acme/api:0.1.0@sha256:abc")
+            ),
+            "api"
+        );
+        assert_eq!(name("api.tgz", Some("localhost:5000/ui")), "ui");
+    }
+}
@@ -0,0 +1,371 @@
+//! The built-in catalog of capability providers that implement well known WIT interfaces.
+//!
+//! Each provider lists the releases expected to implement a version of its WIT package, so
+//! generated manifests can opt in to pinning a provider release that matches the interface
+//! version the component was built against instead of drifting with `canary`. The list is
+//! maintained by hand and isn't checked against the published images, which is why pinning is
+//! opt-in.
+
+use serde::{Deserialize, Serialize};
+
+use crate::interface::Direction;
+
+/// Which tag of a provider image to reference in the manifest
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ImageChannel {
+    /// The `canary` tag, built from the main branch of the provider
+    #[default]
+    Canary,
+    /// The `latest` tag
+    Latest,
+    /// The release matching the WIT interface version, falling back to `canary` when there is
+    /// no known release for that version
+    Pinned,
+}
+
+/// A capability provider that implements a well known WIT package
+pub struct CatalogProvider {
+    pub namespace: &'static str,
+    pub package: &'static str,
+    /// The direction this provider serves, or `None` if it serves both imports and exports
+    pub direction: Option<Direction>,
+    /// The name used to select this provider when several implement the same package, e.g.
+    /// `redis` or `nats` for `wasi:keyvalue`
+    pub backend: &'static str,
+    /// The image repository of the provider, without a tag
+    pub repository: &'static str,
+    /// Provider release tags, keyed by the WIT package version they implement
+    pub releases: &'static [(&'static str, &'static str)],
+    /// Default config for this provider's side of the link when a component imports the
+    /// package, i.e. the link's target config
+    pub import_link_config: &'static [(&'static str, &'static str)],
+    /// Default config for this provider's side of the link when a component exports the
+    /// package, i.e. the link's source config. `{app}` is replaced with the application name
+    pub export_link_config: &'static [(&'static str, &'static str)],
+}
+
+/// Every provider in the catalog. The first provider listed for a package is the default
+/// backend for that package
+pub const PROVIDERS: &[CatalogProvider] = &[
+    CatalogProvider {
+        namespace: "wasi",
+        package: "blobstore",
+        direction: None,
+        backend: "fs",
+        repository: "ghcr.io/wasmcloud/blobstore-fs",
+        releases: &[("0.2.0-draft", "0.6.0")],
+        import_link_config: &[("ROOT", "/tmp")],
+        export_link_config: &[],
+    },
+    CatalogProvider {
+        namespace: "wasi",
+        package: "blobstore",
+        direction: None,
+        backend: "s3",
+        repository: "ghcr.io/wasmcloud/blobstore-s3",
+        releases: &[],
+        import_link_config: &[("AWS_REGION", "us-east-1")],
+        export_link_config: &[],
+    },
+    CatalogProvider {
+        namespace: "wasi",
+        package: "blobstore",
+        direction: None,
+        backend: "azure",
+        repository: "ghcr.io/wasmcloud/blobstore-azure",
+        releases: &[],
+        import_link_config: &[],
+        export_link_config: &[],
+    },
+    CatalogProvider {
+        namespace: "wasi",
+        package: "http",
+        direction: Some(Direction::Import),
+        backend: "client",
+        repository: "ghcr.io/wasmcloud/http-client",
+        releases: &[("0.2.0", "0.10.0")],
+        import_link_config: &[],
+        export_link_config: &[],
+    },
+    CatalogProvider {
+        namespace: "wasi",
+        package: "http",
+        direction: Some(Direction::Export),
+        backend: "server",
+        repository: "ghcr.io/wasmcloud/http-server",
+        releases: &[("0.2.0", "0.20.0")],
+        import_link_config: &[],
+        export_link_config: &[("address", "0.0.0.0:8000")],
+    },
+    CatalogProvider {
+        namespace: "wasi",
+        package: "keyvalue",
+        direction: None,
+        backend: "redis",
+        repository: "ghcr.io/wasmcloud/keyvalue-redis",
+        releases: &[("0.2.0-draft", "0.24.0")],
+        import_link_config: &[("URL", "redis://127.0.0.1:6379")],
+        export_link_config: &[],
+    },
+    CatalogProvider {
+        namespace: "wasi",
+        package: "keyvalue",
+        direction: None,
+        backend: "nats",
+        repository: "ghcr.io/wasmcloud/keyvalue-nats",
+        releases: &[],
+        import_link_config: &[
+            ("cluster_uri", "nats://127.0.0.1:4222"),
+            ("bucket", "WASMCLOUD"),
+        ],
+        export_link_config: &[],
+    },
+    CatalogProvider {
+        namespace: "wasi",
+        package: "keyvalue",
+        direction: None,
+        backend: "vault",
+        repository: "ghcr.io/wasmcloud/keyvalue-vault",
+        releases: &[],
+        import_link_config: &[("addr", "http://127.0.0.1:8200"), ("mount", "secret")],
+        export_link_config: &[],
+    },
+    CatalogProvider {
+        namespace: "wasmcloud",
+        package: "messaging",
+        direction: None,
+        backend: "nats",
+        repository: "ghcr.io/wasmcloud/messaging-nats",
+        releases: &[("0.2.0", "0.19.0")],
+        import_link_config: &[],
+        export_link_config: &[("subscriptions", "{app}.>")],
+    },
+];
+
+/// Finds the provider in the catalog for a WIT package used in the given direction. When no
+/// backend is given, the default backend for the package is returned
+pub fn find_provider(
+    namespace: &str,
+    package: &str,
+    direction: Direction,
+    backend: Option<&str>,
+) -> Option<&'static CatalogProvider> {
+    PROVIDERS.iter().find(|provider| {
+        provider.namespace == namespace
+            && provider.package == package
+            && provider.direction.is_none_or(|d| d == direction)
+            && backend.is_none_or(|b| b == provider.backend)
+    })
+}
+
+/// Returns the names of the backends available for a package, e.g. `keyvalue` or
+/// `wasi:keyvalue`
+pub fn backends(package: &str) -> Vec<&'static str> {
+    let mut backends: Vec<&'static str> = PROVIDERS
+        .iter()
+        .filter(|provider| provider.matches_package(package))
+        .map(|provider| provider.backend)
+        .collect();
+    backends.dedup();
+    backends
+}
+
+/// Renders every provider in the catalog as a table, marking the default backend of each package
+pub fn catalog_table() -> String {
+    let headers = ["PACKAGE", "BACKEND", "SERVES", "IMAGE", "RELEASES"]
+        .map(ToString::to_string)
+        .to_vec();
+    let rows = PROVIDERS
+        .iter()
+        .map(|provider| {
+            vec![
+                format!("{}:{}", provider.namespace, provider.package),
+                if provider.is_default() {
+                    format!("{} (default)", provider.backend)
+                } else {
+                    provider.backend.to_string()
+                },
+                provider.serves().to_string(),
+                provider.repository.to_string(),
+                provider
+                    .releases
+                    .iter()
+                    .map(|(version, tag)| format!("{tag} ({version})"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ]
+        })
+        .collect();
+    crate::explain::render_table(headers, rows)
+}
+
+/// Describes every provider in the catalog for a package, which may be written with or without
+/// its namespace, or returns `None` if the catalog has no provider for it
+pub fn describe_package(package: &str) -> Option<String> {
+    let providers: Vec<&CatalogProvider> = PROVIDERS
+        .iter()
+        .filter(|provider| provider.matches_package(package))
+        .collect();
+    if providers.is_empty() {
+        return None;
+    }
+
+    let mut lines = Vec::new();
+    for provider in providers {
+        lines.push(format!(
+            "{}:{} {}{}",
+            provider.namespace,
+            provider.package,
+            provider.backend,
+            if provider.is_default() {
+                " (default)"
+            } else {
+                ""
+            }
+        ));
+        lines.push(format!("  image: {}", provider.repository));
+        lines.push(format!("  serves: {}", provider.serves()));
+        for (version, tag) in provider.releases {
+            lines.push(format!(
+                "  release {tag} implements {}:{}@{version}",
+                provider.namespace, provider.package
+            ));
+        }
+        for (direction, label) in [
+            (Direction::Import, "imported"),
+            (Direction::Export, "exported"),
+        ] {
+            let config = provider.link_config(direction);
+            if !config.is_empty() {
+                let config = config
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                lines.push(format!("  link config when {label}: {config}"));
+            }
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+impl CatalogProvider {
+    /// Returns true if this provider is the default backend for its package, i.e. the first one
+    /// listed for it
+    pub fn is_default(&self) -> bool {
+        PROVIDERS
+            .iter()
+            .find(|provider| {
+                provider.namespace == self.namespace
+                    && provider.package == self.package
+                    && provider.direction == self.direction
+            })
+            .is_some_and(|provider| provider.backend == self.backend)
+    }
+
+    /// Which of a component's imports and exports of the package this provider serves
+    fn serves(&self) -> &'static str {
+        match self.direction {
+            Some(Direction::Import) => "imports",
+            Some(Direction::Export) => "exports",
+            None => "imports and exports",
+        }
+    }
+
+    /// Returns true if this provider implements the given package, which may be written with or
+    /// without its namespace
+    pub fn matches_package(&self, package: &str) -> bool {
+        match package.split_once(':') {
+            Some((namespace, package)) => self.namespace == namespace && self.package == package,
+            None => self.package == package,
+        }
+    }
+
+    /// The name of the provider without its registry, e.g. `keyvalue-redis`
+    pub fn short_name(&self) -> &'static str {
+        self.repository
+            .rsplit_once('/')
+            .map(|(_, name)| name)
+            .unwrap_or(self.repository)
+    }
+
+    /// The default config for this provider's side of a link in the given direction
+    pub fn link_config(&self, direction: Direction) -> &'static [(&'static str, &'static str)] {
+        match direction {
+            Direction::Import => self.import_link_config,
+            Direction::Export => self.export_link_config,
+        }
+    }
+
+    /// The release tag of this provider for a WIT package version, if there is one
+    pub fn release(&self, version: Option<&str>) -> Option<&'static str> {
+        let version = version?;
+        self.releases
+            .iter()
+            .find_map(|(wit_version, tag)| (*wit_version == version).then_some(*tag))
+    }
+
+    /// Selects the image for this provider for a WIT package version, returning the image and
+    /// a short description of how the tag was chosen
+    pub fn image(&self, version: Option<&str>, channel: ImageChannel) -> (String, String) {
+        match (channel, self.release(version)) {
+            (ImageChannel::Pinned, Some(tag)) => (
+                format!("{}:{tag}", self.repository),
+                format!(
+                    "release {tag} implements version {}",
+                    version.unwrap_or_default()
+                ),
+            ),
+            (ImageChannel::Pinned, None) => (
+                format!("{}:canary", self.repository),
+                format!(
+                    "no known release for version {}, using canary",
+                    version.unwrap_or("(unversioned)")
+                ),
+            ),
+            (ImageChannel::Latest, _) => (
+                format!("{}:latest", self.repository),
+                "latest was requested".to_string(),
+            ),
+            (ImageChannel::Canary, _) => (
+                format!("{}:canary", self.repository),
+                "canary was requested".to_string(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{backends, describe_package, find_provider, ImageChannel};
+    use crate::interface::Direction;
+
+    #[test]
+    fn test_provider_image_selection() {
+        let server = find_provider("wasi", "http", Direction::Export, None).unwrap();
+        assert_eq!(server.repository, "ghcr.io/wasmcloud/http-server");
+
+        let (image, _) = server.image(Some("0.2.0"), ImageChannel::Pinned);
+        assert_eq!(image, "ghcr.io/wasmcloud/http-server:0.20.0");
+        let (image, _) = server.image(Some("0.3.0"), ImageChannel::Pinned);
+        assert_eq!(image, "ghcr.io/wasmcloud/http-server:canary");
+        let (image, _) = server.image(Some("0.2.0"), ImageChannel::Latest);
+        assert_eq!(image, "ghcr.io/wasmcloud/http-server:latest");
+        // Pinning is opt-in
+        let (image, _) = server.image(Some("0.2.0"), ImageChannel::default());
+        assert_eq!(image, "ghcr.io/wasmcloud/http-server:canary");
+
+        assert!(find_provider("acme", "custom", Direction::Import, None).is_none());
+
+        let nats = find_provider("wasi", "keyvalue", Direction::Import, Some("nats")).unwrap();
+        assert_eq!(nats.repository, "ghcr.io/wasmcloud/keyvalue-nats");
+        assert_eq!(backends("wasi:keyvalue"), vec!["redis", "nats", "vault"]);
+        assert!(!nats.is_default() && server.is_default());
+
+        let keyvalue = describe_package("keyvalue").unwrap();
+        assert!(keyvalue.starts_with("wasi:keyvalue redis (default)"));
+        assert!(describe_package("acme:custom").is_none());
+    }
+}
@@ -0,0 +1,220 @@
+//! Checks that an existing manifest still matches a component: every interface the component
+//! needs a provider for is linked, and nothing is linked for an interface the component no
+//! longer imports or exports. This catches hand-edited manifests that drifted from the component
+//! after it was rebuilt.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use anyhow::Context as _;
+use wadm::model::{Manifest, Properties, TraitProperty};
+
+use crate::plan::{ApplicationPlan, Direction};
+
+/// A single way a manifest doesn't match a component
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The manifest doesn't deploy any component
+    NoComponent,
+    /// An interface the component needs a provider for isn't linked
+    MissingLink {
+        direction: Direction,
+        interface: String,
+    },
+    /// An interface is linked that the component doesn't import or export
+    UnusedLink {
+        direction: Direction,
+        interface: String,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::NoComponent => write!(f, "manifest doesn't deploy a component"),
+            Mismatch::MissingLink {
+                direction: Direction::Import,
+                interface,
+            } => write!(f, "component imports {interface} but it isn't linked"),
+            Mismatch::MissingLink {
+                direction: Direction::Export,
+                interface,
+            } => write!(f, "component exports {interface} but nothing links to it"),
+            Mismatch::UnusedLink {
+                direction: Direction::Import,
+                interface,
+            } => write!(
+                f,
+                "{interface} is linked but the component doesn't import it"
+            ),
+            Mismatch::UnusedLink {
+                direction: Direction::Export,
+                interface,
+            } => write!(
+                f,
+                "{interface} links to the component but it doesn't export it"
+            ),
+        }
+    }
+}
+
+/// The error returned when a manifest doesn't match a component
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckError {
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "manifest doesn't match the component in {} way(s):",
+            self.mismatches.len()
+        )?;
+        for mismatch in &self.mismatches {
+            write!(f, "\n  - {mismatch}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+/// Parses a manifest written as YAML or JSON
+pub fn parse_manifest(contents: &str) -> anyhow::Result<Manifest> {
+    serde_yaml::from_str(contents).context("failed to parse manifest")
+}
+
+/// A linked interface: its direction, namespace, package and interface name
+type LinkedInterface = (Direction, String, String, String);
+
+/// Checks the links in a manifest against the links planned for a component, returning every
+/// mismatch that was found. Only the interfaces are compared, so the manifest is free to use its
+/// own names, images and config
+pub fn check_manifest(manifest: &Manifest, plan: &ApplicationPlan) -> Result<(), CheckError> {
+    let components: Vec<&str> = manifest
+        .spec
+        .components
+        .iter()
+        .filter(|component| matches!(component.properties, Properties::Component { .. }))
+        .map(|component| component.name.as_str())
+        .collect();
+    if components.is_empty() {
+        return Err(CheckError {
+            mismatches: vec![Mismatch::NoComponent],
+        });
+    }
+
+    let mut linked = BTreeSet::new();
+    for component in &manifest.spec.components {
+        let links = component
+            .traits
+            .iter()
+            .flatten()
+            .filter_map(|t| match &t.properties {
+                TraitProperty::Link(link) => Some(link),
+                _ => None,
+            });
+        for link in links {
+            let direction = if components.contains(&component.name.as_str()) {
+                Direction::Import
+            } else if components.contains(&link.target.as_str()) {
+                Direction::Export
+            } else {
+                continue;
+            };
+            linked.extend(link.interfaces.iter().map(|interface| {
+                (
+                    direction,
+                    link.namespace.clone(),
+                    link.package.clone(),
+                    interface.clone(),
+                )
+            }));
+        }
+    }
+
+    let planned_components: Vec<&str> = plan.components.iter().map(|c| c.name.as_str()).collect();
+    let mut planned = BTreeSet::new();
+    for link in &plan.links {
+        let direction = if planned_components.contains(&link.source.as_str()) {
+            Direction::Import
+        } else {
+            Direction::Export
+        };
+        planned.extend(link.interfaces.iter().map(|interface| {
+            (
+                direction,
+                link.namespace.clone(),
+                link.package.clone(),
+                interface.clone(),
+            )
+        }));
+    }
+
+    let name = |(_, namespace, package, interface): &LinkedInterface| {
+        format!("{namespace}:{package}/{interface}")
+    };
+    let missing = planned
+        .difference(&linked)
+        .map(|entry| Mismatch::MissingLink {
+            direction: entry.0,
+            interface: name(entry),
+        });
+    let unused = linked
+        .difference(&planned)
+        .map(|entry| Mismatch::UnusedLink {
+            direction: entry.0,
+            interface: name(entry),
+        });
+    let mismatches: Vec<Mismatch> = missing.chain(unused).collect();
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(CheckError { mismatches })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_manifest, Mismatch};
+    use crate::plan::{plan_application, Direction};
+    use crate::{plan_to_wadm, GenerateOptions};
+
+    #[test]
+    fn test_check_manifest() {
+        let plan = |imports: &[&str]| {
+            plan_application(
+                "echo",
+                "file://./echo.wasm",
+                &imports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                &["wasi:http/incoming-handler@0.2.0".to_string()],
+                &GenerateOptions::default(),
+            )
+        };
+        let manifest = plan_to_wadm(
+            &plan(&["wasi:keyvalue/store@0.2.0-draft"]),
+            "An Echo Application",
+            "v0.1.0",
+        );
+
+        assert!(check_manifest(&manifest, &plan(&["wasi:keyvalue/store@0.2.0-draft"])).is_ok());
+
+        let rebuilt = plan(&["wasi:blobstore/blobstore@0.2.0-draft"]);
+        let err = check_manifest(&manifest, &rebuilt).unwrap_err();
+        assert_eq!(
+            err.mismatches,
+            vec![
+                Mismatch::MissingLink {
+                    direction: Direction::Import,
+                    interface: "wasi:blobstore/blobstore".to_string(),
+                },
+                Mismatch::UnusedLink {
+                    direction: Direction::Import,
+                    interface: "wasi:keyvalue/store".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -1,17 +1,99 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use anyhow::Context as _;
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::catalog::{self, ImageChannel};
+use crate::compose::HttpRouting;
+use crate::explain::{explain_json, explain_table};
+use crate::plan::ApplicationPlan;
+use crate::render::OutputFormat;
+use crate::settings::Settings;
+use crate::{GenerateOptions, Subscription};
 
 #[derive(Parser)]
-#[clap(author = "wasmCloud", arg_required_else_help = true)]
+#[clap(author = "wasmCloud", args_conflicts_with_subcommands = true)]
 /// wit2wadm is a tool for converting a WIT directory or a WebAssembly component into a deployable https://github.com/wasmcloud/wadm manifests.
 /// Either supply a WIT folder and a world name or a WebAssembly component and the resulting manifest will be printed to stdout.
 /// See https://github.com/brooksmtownsend/wit2wadm for more information.
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+    /// Without a subcommand, the arguments of `generate`
+    #[clap(flatten)]
+    pub generate: Args,
+}
+
+impl Cli {
+    /// The command to run, which is `generate` when no subcommand was given
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Generate(self.generate))
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generate a manifest for a WIT world or component. This is the default
+    Generate(Args),
+    /// List the imports and exports of a WIT world or component and how each is handled in a
+    /// manifest. Takes the same arguments as `generate`
+    Inspect(Args),
+    /// Check that an existing manifest links everything a component needs and nothing else,
+    /// e.g. after the component was rebuilt. Takes the same arguments as `generate`
+    Check(CheckArgs),
+    /// Print the built-in catalog of providers for well known interfaces
+    #[clap(subcommand)]
+    Catalog(CatalogCommand),
+    /// Add or update the lockfile entries for providers stored as OCI image layout directories
+    /// or tarballs of them
+    Lock(LockArgs),
+}
+
+/// The arguments of `check`
+#[derive(clap::Args)]
+pub struct CheckArgs {
+    /// The manifest to check
+    #[clap(long = "manifest", conflicts_with_all = ["batch", "explain"])]
+    pub manifest: PathBuf,
+    #[clap(flatten)]
+    pub generate: Args,
+}
+
+/// The arguments of `lock`
+#[derive(clap::Args)]
+pub struct LockArgs {
+    /// The OCI image layout directories or tarballs of the providers to lock
+    #[clap(required = true)]
+    pub layouts: Vec<PathBuf>,
+    /// The lockfile to update, which is created if it doesn't exist. Defaults to
+    /// `wit2wadm.lock` in the current directory
+    #[clap(long = "lockfile")]
+    pub lockfile: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum CatalogCommand {
+    /// List every provider in the catalog
+    List,
+    /// Show the providers for a package, e.g. `keyvalue` or `wasi:keyvalue`
+    Show {
+        /// The package, with or without its namespace
+        package: String,
+    },
+}
+
+/// The arguments for generating a manifest, shared by `generate`, `inspect` and `check`
+#[derive(clap::Args)]
 pub struct Args {
     #[clap(name = "wit_folder_or_component")]
-    /// The path to a WIT folder including dependencies and a world, or a built Wasm component
-    pub wit_folder_or_component: PathBuf,
-    /// The world name to use to convert to a manifest, required if a WIT folder is provided
+    /// The path to a WIT folder including dependencies and a world, or a built Wasm component. The
+    /// component may also be an OCI image layout directory or a tarball of one, a binary-encoded
+    /// WIT package or a core module built for a component. Use `-` to read it from stdin. Defaults
+    /// to the component built by the project described by `wasmcloud.toml` in the current
+    /// directory, which also provides the default name and version
+    pub wit_folder_or_component: Option<PathBuf>,
+    /// The world name to use to convert to a manifest, required if a WIT folder or a WIT package
+    /// with several worlds is provided
     #[clap(name = "world_name")]
     pub world_name: Option<String>,
     /// The name of the application to use in the manifest
@@ -23,7 +105,383 @@ pub struct Args {
     /// The version of the application to use in the manifest
     #[clap(long = "app-version")]
     pub app_version: Option<String>,
-    /// The image to use in the manifest
+    /// The image to use in the manifest. Defaults to the annotated, digest-pinned reference when
+    /// the component is read from an OCI artifact
     #[clap(long = "image")]
     pub app_image: Option<String>,
+    /// Instead of printing a manifest, print how each import and export was mapped, dropped or
+    /// left as a placeholder, and the rule that produced that decision
+    #[clap(
+        long = "explain",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "table"
+    )]
+    pub explain: Option<ExplainFormat>,
+    /// The format to print manifests in. Several manifests are printed as a multi-document YAML
+    /// stream or a JSON array
+    #[clap(long = "format", value_enum)]
+    pub format: Option<OutputFormat>,
+    /// Write the output to this file instead of printing it
+    #[clap(short = 'o', long = "output", conflicts_with = "out_dir")]
+    pub output: Option<PathBuf>,
+    /// Write each manifest to its own file, named after the application, in this directory
+    #[clap(long = "out-dir")]
+    pub out_dir: Option<PathBuf>,
+    /// Overwrite files that already exist when writing output
+    #[clap(long = "force")]
+    pub force: bool,
+    /// Fail instead of printing the manifest if it still contains placeholder images, canary
+    /// tags or default names. Pass `--strict=false` to turn off `strict` from the settings file
+    #[clap(
+        long = "strict",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub strict: Option<bool>,
+    /// Which tag of well known provider images to use. Defaults to `canary`, while `pinned`
+    /// selects the provider release matching the WIT interface version
+    #[clap(long = "provider-images", value_enum)]
+    pub provider_images: Option<ImageChannel>,
+    /// The lockfile used to pin provider images to digests. Defaults to `wit2wadm.lock` in the
+    /// current directory, which is used if it exists
+    #[clap(long = "lockfile")]
+    pub lockfile: Option<PathBuf>,
+    /// Select the provider for a package with several known providers, e.g. `keyvalue=nats` or
+    /// `wasi:blobstore=s3`. Can be repeated
+    #[clap(long = "backend", value_parser = parse_backend)]
+    pub backends: Vec<(String, String)>,
+    /// Link an interface or package to a provider image, even one normally implemented by the
+    /// host, e.g. `wasi:logging/logging=ghcr.io/acme/logging:1.0.0`. Can be repeated
+    #[clap(long = "provider", value_parser = parse_provider_override)]
+    pub provider_overrides: Vec<(String, String)>,
+    /// The template for provider names, using `{app}`, `{namespace}`, `{package}`, `{version}`
+    /// and `{direction}`, e.g. `{package}-{direction}`. Defaults to
+    /// `{namespace}:{package}-{direction}`
+    #[clap(long = "provider-name")]
+    pub provider_name: Option<String>,
+    /// The template for link names, using the same placeholders as `--provider-name`
+    #[clap(long = "link-name")]
+    pub link_name: Option<String>,
+    /// Keep separate providers for the imports and exports of a package, e.g. `messaging`,
+    /// instead of one provider serving both. Can be repeated
+    #[clap(long = "separate-providers")]
+    pub separate_providers: Vec<String>,
+    /// Leave an interface or package out of the manifest, e.g. `acme:sidecar/tools` or
+    /// `acme:sidecar`. Can be repeated
+    #[clap(long = "ignore")]
+    pub ignore: Vec<String>,
+    /// Treat a package as another one, e.g. `acme:kv=wasi:keyvalue` or
+    /// `acme:kv=wasi:keyvalue@0.2.0-draft`. Can be repeated
+    #[clap(long = "alias", value_parser = parse_alias)]
+    pub aliases: Vec<(String, String)>,
+    /// Set config on the provider's side of the link for a package, e.g.
+    /// `http.address=0.0.0.0:8080`. Overrides the defaults for well known providers and the
+    /// provider config file. Can be repeated
+    #[clap(long = "provider-config", value_parser = parse_provider_config)]
+    pub provider_config: Vec<(String, String, String)>,
+    /// A TOML file with a table of provider link config per package
+    #[clap(long = "provider-config-file")]
+    pub provider_config_file: Option<PathBuf>,
+    /// Convert each of these components into its own application, named after its file, instead
+    /// of converting a single input. A directory converts every `.wasm` file and OCI artifact in
+    /// it and a `*` in a file name matches several files, e.g. `build/*.wasm`
+    #[clap(
+        long = "batch",
+        num_args = 1..,
+        conflicts_with_all = [
+            "wit_folder_or_component",
+            "world_name",
+            "app_name",
+            "app_image",
+            "explain",
+            "with",
+            "emit_shared",
+        ]
+    )]
+    pub batch: Vec<PathBuf>,
+    /// Another component to include in the application, named after its file. Can be repeated
+    #[clap(long = "with")]
+    pub with: Vec<PathBuf>,
+    /// Also emit the well known providers the application uses as a separate shared application
+    /// with this name, for lattices that run those providers once. The application still runs
+    /// its own providers, see [shared](crate::shared)
+    #[clap(long = "emit-shared")]
+    pub emit_shared: Option<String>,
+    /// Only put the provider for this package in the shared application, e.g. `http`. Can be
+    /// repeated
+    #[clap(long = "shared", requires = "emit_shared")]
+    pub shared_packages: Vec<String>,
+    /// How requests are routed when several components export `wasi:http`. `none` gives each
+    /// component its own HTTP server on its own port
+    #[clap(long = "http-routing", value_enum)]
+    pub http_routing: Option<HttpRouting>,
+    /// The HTTP route of a component, e.g. `api=/api` when routing by path, `api=api.example.com`
+    /// when routing by host or `api=8081` otherwise. Can be repeated
+    #[clap(long = "http-route", value_parser = parse_http_route)]
+    pub http_routes: Vec<(String, String)>,
+    /// A subject for components exporting `wasmcloud:messaging/handler` to subscribe to, e.g.
+    /// `orders.>`, optionally with a queue group as `orders.>=workers`. Can be repeated
+    #[clap(long = "subscribe", value_parser = parse_subscription)]
+    pub subscriptions: Vec<Subscription>,
+    /// Config for components that import `wasi:config`, e.g. `greeting=hello`. Overrides the
+    /// config file. Can be repeated
+    #[clap(long = "config", value_parser = parse_config)]
+    pub config: Vec<(String, String)>,
+    /// A TOML file of config for components that import `wasi:config`
+    #[clap(long = "config-file")]
+    pub config_file: Option<PathBuf>,
+    /// A settings file holding any of these options, which flags override. Defaults to
+    /// `wit2wadm.toml` in the current directory, which is used if it exists
+    #[clap(long = "settings")]
+    pub settings: Option<PathBuf>,
+}
+
+impl Args {
+    /// The [GenerateOptions] selected by these arguments. Files referenced by the arguments, like
+    /// the lockfile and config files, are left for the caller to load
+    pub fn generate_options(&self) -> GenerateOptions {
+        self.merge_options(GenerateOptions::default())
+    }
+
+    /// Fills in the arguments that weren't given from a settings file and returns the
+    /// [GenerateOptions] from the file with these arguments applied over them
+    pub fn apply_settings(&mut self, settings: Settings) -> GenerateOptions {
+        self.app_name = self.app_name.take().or(settings.name);
+        self.app_description = self.app_description.take().or(settings.description);
+        self.app_version = self.app_version.take().or(settings.version);
+        self.app_image = self.app_image.take().or(settings.image);
+        self.world_name = self.world_name.take().or(settings.world);
+        self.format = self.format.or(settings.format);
+        self.strict = self.strict.or(settings.strict);
+        self.lockfile = self.lockfile.take().or(settings.lockfile);
+        self.config_file = self.config_file.take().or(settings.config_file);
+        self.provider_config_file = self
+            .provider_config_file
+            .take()
+            .or(settings.provider_config_file);
+        self.merge_options(settings.options)
+    }
+
+    /// Applies these arguments over existing options. Options keyed by package or name are
+    /// merged with the arguments winning, while other options given as arguments replace the
+    /// existing ones
+    pub fn merge_options(&self, mut options: GenerateOptions) -> GenerateOptions {
+        for (package, key, value) in &self.provider_config {
+            options
+                .provider_config
+                .entry(package.clone())
+                .or_default()
+                .insert(key.clone(), value.clone());
+        }
+        options.backends.extend(self.backends.iter().cloned());
+        options
+            .provider_overrides
+            .extend(self.provider_overrides.iter().cloned());
+        options.aliases.extend(self.aliases.iter().cloned());
+        options.http_routes.extend(self.http_routes.iter().cloned());
+        options.config.extend(self.config.iter().cloned());
+
+        if let Some(provider_images) = self.provider_images {
+            options.provider_images = provider_images;
+        }
+        if let Some(http_routing) = self.http_routing {
+            options.http_routing = http_routing;
+        }
+        replace_if_set(&mut options.provider_name, &self.provider_name);
+        replace_if_set(&mut options.link_name, &self.link_name);
+        replace_if_given(&mut options.separate_providers, &self.separate_providers);
+        replace_if_given(&mut options.ignore, &self.ignore);
+        replace_if_given(&mut options.subscriptions, &self.subscriptions);
+        options
+    }
+}
+
+fn replace_if_set<T: Clone>(option: &mut Option<T>, arg: &Option<T>) {
+    if arg.is_some() {
+        option.clone_from(arg);
+    }
+}
+
+fn replace_if_given<T: Clone>(option: &mut Vec<T>, arg: &[T]) {
+    if !arg.is_empty() {
+        *option = arg.to_vec();
+    }
+}
+
+/// Parses a `PACKAGE=BACKEND` pair, checking that the backend is in the catalog
+fn parse_backend(s: &str) -> Result<(String, String), String> {
+    let (package, backend) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected PACKAGE=BACKEND, got {s}"))?;
+    let available = catalog::backends(package);
+    if available.is_empty() {
+        return Err(format!("no known providers for package {package}"));
+    }
+    if !available.contains(&backend) {
+        return Err(format!(
+            "unknown backend {backend} for {package}, expected one of: {}",
+            available.join(", ")
+        ));
+    }
+    Ok((package.to_string(), backend.to_string()))
+}
+
+/// Parses an `INTERFACE=IMAGE` pair, where the interface is `namespace:package/interface` or
+/// `namespace:package`
+fn parse_provider_override(s: &str) -> Result<(String, String), String> {
+    let (interface, image) = s
+        .split_once('=')
+        .filter(|(interface, image)| interface.contains(':') && !image.is_empty())
+        .ok_or_else(|| format!("expected NAMESPACE:PACKAGE[/INTERFACE]=IMAGE, got {s}"))?;
+    Ok((interface.to_string(), image.to_string()))
+}
+
+/// Parses a `NAMESPACE:PACKAGE=NAMESPACE:PACKAGE[@VERSION]` alias
+fn parse_alias(s: &str) -> Result<(String, String), String> {
+    let (package, target) = s
+        .split_once('=')
+        .filter(|(package, target)| package.contains(':') && target.contains(':'))
+        .ok_or_else(|| format!("expected NAMESPACE:PACKAGE=NAMESPACE:PACKAGE, got {s}"))?;
+    Ok((package.to_string(), target.to_string()))
+}
+
+/// Parses a `PACKAGE.KEY=VALUE` provider config entry
+fn parse_provider_config(s: &str) -> Result<(String, String, String), String> {
+    let (path, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected PACKAGE.KEY=VALUE, got {s}"))?;
+    let (package, key) = path
+        .split_once('.')
+        .filter(|(package, key)| !package.is_empty() && !key.is_empty())
+        .ok_or_else(|| format!("expected PACKAGE.KEY=VALUE, got {s}"))?;
+    Ok((package.to_string(), key.to_string(), value.to_string()))
+}
+
+/// Parses a `COMPONENT=ROUTE` pair
+fn parse_http_route(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(component, route)| (component.to_string(), route.to_string()))
+        .ok_or_else(|| format!("expected COMPONENT=ROUTE, got {s}"))
+}
+
+/// Parses a `KEY=VALUE` config entry
+fn parse_config(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got {s}"))
+}
+
+/// Parses a `SUBJECT` or `SUBJECT=QUEUE` subscription
+fn parse_subscription(s: &str) -> Result<Subscription, String> {
+    let (subject, queue) = match s.split_once('=') {
+        Some((subject, queue)) => (subject, Some(queue.to_string())),
+        None => (s, None),
+    };
+    if subject.is_empty() || queue.as_deref() == Some("") {
+        return Err(format!("expected SUBJECT or SUBJECT=QUEUE, got {s}"));
+    }
+    Ok(Subscription {
+        subject: subject.to_string(),
+        queue,
+    })
+}
+
+/// The output format of `--explain`
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExplainFormat {
+    /// A human readable table
+    Table,
+    /// JSON, one entry per import and export
+    Json,
+}
+
+impl ExplainFormat {
+    /// Renders the explanation of a plan in this format
+    pub fn render(&self, plan: &ApplicationPlan) -> anyhow::Result<String> {
+        match self {
+            ExplainFormat::Table => Ok(explain_table(plan)),
+            ExplainFormat::Json => explain_json(plan).context("failed to serialize explanation"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use clap::Parser as _;
+
+    use super::{parse_backend, Cli, Command};
+    use crate::settings::Settings;
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!(
+            parse_backend("keyvalue=nats"),
+            Ok(("keyvalue".to_string(), "nats".to_string()))
+        );
+        assert_eq!(
+            parse_backend("wasi:blobstore=s3"),
+            Ok(("wasi:blobstore".to_string(), "s3".to_string()))
+        );
+        assert_eq!(
+            parse_backend("keyvalue=etcd"),
+            Err(
+                "unknown backend etcd for keyvalue, expected one of: redis, nats, vault"
+                    .to_string()
+            )
+        );
+        assert!(parse_backend("acme:cache=redis").is_err());
+        assert!(parse_backend("keyvalue").is_err());
+    }
+
+    #[test]
+    fn test_apply_settings() {
+        let settings = Settings::parse(
+            r#"
+            name = "shop"
+            strict = true
+            lockfile = "locks/wit2wadm.lock"
+            ignore = ["acme:sidecar"]
+            "#,
+        )
+        .unwrap();
+        let apply = |args: &[&str]| {
+            let Command::Generate(mut args) =
+                Cli::try_parse_from([&["wit2wadm", "echo.wasm"][..], args].concat())
+                    .unwrap()
+                    .into_command()
+            else {
+                panic!("expected the generate command");
+            };
+            let options = args.apply_settings(settings.clone());
+            (args, options)
+        };
+
+        let (args, options) = apply(&[]);
+        assert_eq!(args.strict, Some(true));
+        assert_eq!(args.app_name.as_deref(), Some("shop"));
+        assert_eq!(args.lockfile, Some(PathBuf::from("locks/wit2wadm.lock")));
+        assert_eq!(options.ignore, vec!["acme:sidecar"]);
+
+        // Flags win over the file, including turning strict off
+        let (args, options) = apply(&[
+            "--strict=false",
+            "--name",
+            "store",
+            "--lockfile",
+            "wit2wadm.lock",
+            "--ignore",
+            "acme:tools",
+        ]);
+        assert_eq!(args.strict, Some(false));
+        assert_eq!(args.app_name.as_deref(), Some("store"));
+        assert_eq!(args.lockfile, Some(PathBuf::from("wit2wadm.lock")));
+        assert_eq!(options.ignore, vec!["acme:tools"]);
+
+        assert_eq!(apply(&["--strict"]).0.strict, Some(true));
+    }
 }
@@ -0,0 +1,555 @@
+//! Combining the plans of several components into a single application. Providers that more than
+//! one component needs are shared, while components that export `wasi:http` either get an HTTP
+//! server each on its own port or share one that routes requests to them by path or host.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Context as _};
+use serde::{Deserialize, Serialize};
+
+use crate::naming;
+use crate::plan::{ApplicationPlan, Classification, ConfigPlan, Direction, ProviderPlan};
+use crate::GenerateOptions;
+
+/// The property of the HTTP server's config holding the address it listens on
+const ADDRESS_PROPERTY: &str = "address";
+/// The address the HTTP server listens on when its config doesn't set one
+const DEFAULT_ADDRESS: &str = "0.0.0.0:8000";
+
+/// How requests are routed when several components export `wasi:http`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum HttpRouting {
+    /// Every component gets its own HTTP server listening on its own port
+    #[default]
+    None,
+    /// One HTTP server routes requests to components by path, e.g. `/api`
+    Path,
+    /// One HTTP server routes requests to components by host, e.g. `api.example.com`
+    Host,
+}
+
+impl HttpRouting {
+    /// The route used for a component when none was given
+    fn default_route(&self, component: &str) -> String {
+        match self {
+            HttpRouting::None => DEFAULT_ADDRESS.to_string(),
+            HttpRouting::Path => format!("/{component}"),
+            HttpRouting::Host => format!("{component}.localhost"),
+        }
+    }
+}
+
+/// Combines the plans of several components into one application with the given name.
+///
+/// Providers with the same name and image are shared by the components that need them, while a
+/// provider that needs a different image for different components is split in two, with the
+/// version appended to the name of the second one.
+/// HTTP servers are split or shared depending on [GenerateOptions::http_routing], with the
+/// per-component routes taken from [GenerateOptions::http_routes]
+pub fn compose_application(
+    name: &str,
+    plans: Vec<ApplicationPlan>,
+    options: &GenerateOptions,
+) -> anyhow::Result<ApplicationPlan> {
+    let mut app = ApplicationPlan {
+        name: name.to_string(),
+        components: Vec::new(),
+        interfaces: Vec::new(),
+        providers: Vec::new(),
+        links: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    for mut plan in plans {
+        for component in &plan.components {
+            if app.components.iter().any(|c| c.name == component.name) {
+                bail!("more than one component is named {}", component.name);
+            }
+        }
+        for mut provider in plan.providers.clone() {
+            let shared = app
+                .providers
+                .iter()
+                .find(|p| p.name == provider.name)
+                .map(|p| p.image.clone());
+            if shared.is_some_and(|image| image != provider.image) {
+                let name = versioned_provider_name(&app, &plan, &provider);
+                rename_provider(&mut plan, &provider.name, &name);
+                provider.name = name;
+            }
+            match app.providers.iter_mut().find(|p| p.name == provider.name) {
+                Some(existing) => {
+                    for interface in provider.interfaces {
+                        if !existing.interfaces.contains(&interface) {
+                            existing.interfaces.push(interface);
+                        }
+                    }
+                }
+                None => app.providers.push(provider),
+            }
+        }
+        app.components.extend(plan.components);
+        app.interfaces.extend(plan.interfaces);
+        app.links.extend(plan.links);
+        app.warnings.extend(plan.warnings);
+    }
+
+    let http_links = http_server_links(&app);
+    for component in options.http_routes.keys() {
+        if !http_links
+            .iter()
+            .any(|&i| app.links[i].target == *component)
+        {
+            bail!("an HTTP route was given for {component}, which doesn't export wasi:http");
+        }
+    }
+    match options.http_routing {
+        HttpRouting::None => allocate_ports(&mut app, &http_links, options)?,
+        routing => route_requests(&mut app, &http_links, routing, options)?,
+    }
+
+    naming::disambiguate_links(&mut app.links);
+    Ok(app)
+}
+
+/// Names a provider that needs a different image than the provider of the same name another
+/// component uses, by appending its version the way [plan_application] does for one component
+/// importing several versions of a package. A provider already named that way with the same
+/// image is shared
+///
+/// [plan_application]: crate::plan::plan_application
+fn versioned_provider_name(
+    app: &ApplicationPlan,
+    plan: &ApplicationPlan,
+    provider: &ProviderPlan,
+) -> String {
+    if let Some(version) = &provider.version {
+        let versioned = format!("{}-{version}", provider.name);
+        if app
+            .providers
+            .iter()
+            .any(|p| p.name == versioned && p.image == provider.image)
+        {
+            return versioned;
+        }
+    }
+    let taken: Vec<String> = app
+        .components
+        .iter()
+        .chain(&plan.components)
+        .map(|c| c.name.clone())
+        .chain(
+            app.providers
+                .iter()
+                .chain(&plan.providers)
+                .map(|p| p.name.clone()),
+        )
+        .collect();
+    naming::unique_name(provider.name.clone(), provider.version.as_deref(), &taken)
+}
+
+/// Renames a provider in the links and interface decisions of a component's plan
+fn rename_provider(plan: &mut ApplicationPlan, from: &str, to: &str) {
+    for link in plan.links.iter_mut() {
+        for end in [&mut link.source, &mut link.target] {
+            if *end == from {
+                *end = to.to_string();
+            }
+        }
+    }
+    for decision in plan.interfaces.iter_mut() {
+        if let Classification::Provider { provider, .. } | Classification::Custom { provider, .. } =
+            &mut decision.classification
+        {
+            if *provider == from {
+                *provider = to.to_string();
+            }
+        }
+    }
+}
+
+fn is_http_server(provider: &ProviderPlan) -> bool {
+    provider.namespace == "wasi"
+        && provider.package == "http"
+        && provider.direction == Direction::Export
+}
+
+/// Returns the indices of the links from an HTTP server to a component
+fn http_server_links(app: &ApplicationPlan) -> Vec<usize> {
+    app.links
+        .iter()
+        .enumerate()
+        .filter(|(_, link)| {
+            app.providers
+                .iter()
+                .any(|p| p.name == link.source && is_http_server(p))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Gives every component exporting `wasi:http` its own HTTP server. Components keep the
+/// configured address when it's free and are moved to the next free port otherwise, unless an
+/// address (or just a port) was given for them, in which case a clash is an error
+fn allocate_ports(
+    app: &mut ApplicationPlan,
+    http_links: &[usize],
+    options: &GenerateOptions,
+) -> anyhow::Result<()> {
+    if http_links.len() > 1 {
+        split_http_servers(app, http_links);
+    }
+
+    let mut used: BTreeMap<u16, String> = BTreeMap::new();
+    let mut addresses: HashMap<usize, String> = HashMap::new();
+    // Explicit addresses are placed first so automatically allocated ports never take them
+    for &i in http_links {
+        let link = &app.links[i];
+        let Some(route) = options.http_routes.get(&link.target) else {
+            continue;
+        };
+        let configured = link_address(app, i);
+        let address = match route.parse::<u16>() {
+            Ok(port) => with_port(&configured, port)?,
+            Err(_) => route.clone(),
+        };
+        let port = address_port(&address)?;
+        if let Some(other) = used.insert(port, link.target.clone()) {
+            bail!(
+                "components {other} and {} are both configured to listen on port {port}",
+                link.target
+            );
+        }
+        addresses.insert(i, address);
+    }
+    for &i in http_links {
+        if addresses.contains_key(&i) {
+            continue;
+        }
+        let configured = link_address(app, i);
+        let mut port = address_port(&configured)?;
+        while used.contains_key(&port) {
+            port = port
+                .checked_add(1)
+                .context("ran out of ports to allocate to HTTP servers")?;
+        }
+        used.insert(port, app.links[i].target.clone());
+        addresses.insert(i, with_port(&configured, port)?);
+    }
+
+    for (i, address) in addresses {
+        let target = app.links[i].target.clone();
+        set_link_property(app, i, &target, ADDRESS_PROPERTY, address);
+    }
+    Ok(())
+}
+
+/// Replaces the HTTP server shared by several components with one per component, named after
+/// the component it serves
+fn split_http_servers(app: &mut ApplicationPlan, http_links: &[usize]) {
+    let mut taken: Vec<String> = app
+        .components
+        .iter()
+        .map(|c| c.name.clone())
+        .chain(app.providers.iter().map(|p| p.name.clone()))
+        .collect();
+    for &i in http_links {
+        let link = &app.links[i];
+        let Some(provider) = app.providers.iter().find(|p| p.name == link.source) else {
+            continue;
+        };
+        let name = naming::component_provider_name(&link.target, &provider.name, &taken);
+        taken.push(name.clone());
+        let split = ProviderPlan {
+            name: name.clone(),
+            ..provider.clone()
+        };
+
+        for decision in app.interfaces.iter_mut() {
+            if decision.component != link.target {
+                continue;
+            }
+            if let Classification::Provider { provider, .. } = &mut decision.classification {
+                if *provider == link.source {
+                    *provider = name.clone();
+                }
+            }
+        }
+        let position = app
+            .providers
+            .iter()
+            .position(|p| p.name == link.source)
+            .unwrap_or(app.providers.len());
+        app.providers.insert(position, split);
+        app.links[i].source = name;
+    }
+
+    // Drop the shared servers, which no longer have any links
+    let sources: Vec<String> = app.links.iter().map(|l| l.source.clone()).collect();
+    app.providers
+        .retain(|p| !is_http_server(p) || sources.contains(&p.name));
+}
+
+/// Routes requests to every component exporting `wasi:http` through a single HTTP server, with
+/// the path or host of each component set on its link
+fn route_requests(
+    app: &mut ApplicationPlan,
+    http_links: &[usize],
+    routing: HttpRouting,
+    options: &GenerateOptions,
+) -> anyhow::Result<()> {
+    let Some(&first) = http_links.first() else {
+        return Ok(());
+    };
+    let server = app.links[first].source.clone();
+    let address = link_address(app, first);
+
+    let property = match routing {
+        HttpRouting::Path => "path",
+        HttpRouting::Host => "host",
+        HttpRouting::None => unreachable!("routing is only done by path or host"),
+    };
+    let mut routes: BTreeMap<String, String> = BTreeMap::new();
+    for &i in http_links {
+        let target = app.links[i].target.clone();
+        let route = options
+            .http_routes
+            .get(&target)
+            .cloned()
+            .unwrap_or_else(|| routing.default_route(&target));
+        if routing == HttpRouting::Path && !route.starts_with('/') {
+            bail!("the HTTP path for {target} must start with a '/', got {route}");
+        }
+        if let Some(other) = routes.insert(route.clone(), target.clone()) {
+            bail!("components {other} and {target} are both routed to {route}");
+        }
+
+        // The address belongs to the shared server now, so only the route is left on the link
+        let link = &mut app.links[i];
+        link.source = server.clone();
+        for config in link.source_config.iter_mut() {
+            config.properties.remove(ADDRESS_PROPERTY);
+        }
+        link.source_config
+            .retain(|config| !config.properties.is_empty());
+        link.source_config.push(ConfigPlan {
+            name: format!("{target}-http-route"),
+            properties: BTreeMap::from([(property.to_string(), route)]),
+        });
+    }
+
+    let sources: Vec<String> = app.links.iter().map(|l| l.source.clone()).collect();
+    app.providers
+        .retain(|p| !is_http_server(p) || sources.contains(&p.name));
+    if let Some(provider) = app.providers.iter_mut().find(|p| p.name == server) {
+        provider.config = vec![ConfigPlan {
+            name: format!("{}-http-server", app.name),
+            properties: BTreeMap::from([
+                (ADDRESS_PROPERTY.to_string(), address),
+                ("routing_mode".to_string(), property.to_string()),
+            ]),
+        }];
+    }
+    Ok(())
+}
+
+/// The address configured on an HTTP server link, or the default if there isn't one
+fn link_address(app: &ApplicationPlan, link: usize) -> String {
+    app.links[link]
+        .source_config
+        .iter()
+        .find_map(|config| config.properties.get(ADDRESS_PROPERTY))
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string())
+}
+
+/// Sets a property on the source config of a link, adding named config for the component if
+/// the link doesn't have any
+fn set_link_property(
+    app: &mut ApplicationPlan,
+    link: usize,
+    component: &str,
+    key: &str,
+    value: String,
+) {
+    let link = &mut app.links[link];
+    if link.source_config.is_empty() {
+        link.source_config.push(ConfigPlan {
+            name: format!("{component}-http-source"),
+            properties: BTreeMap::new(),
+        });
+    }
+    for config in link.source_config.iter_mut() {
+        config.properties.remove(key);
+    }
+    link.source_config[0]
+        .properties
+        .insert(key.to_string(), value);
+}
+
+fn address_port(address: &str) -> anyhow::Result<u16> {
+    address
+        .rsplit_once(':')
+        .and_then(|(_, port)| port.parse().ok())
+        .with_context(|| format!("HTTP address {address} must be HOST:PORT"))
+}
+
+fn with_port(address: &str, port: u16) -> anyhow::Result<String> {
+    let (host, _) = address
+        .rsplit_once(':')
+        .with_context(|| format!("HTTP address {address} must be HOST:PORT"))?;
+    Ok(format!("{host}:{port}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compose_application, HttpRouting};
+    use crate::plan::plan_application;
+    use crate::GenerateOptions;
+
+    fn http_component(name: &str, options: &GenerateOptions) -> crate::plan::ApplicationPlan {
+        plan_application(
+            name,
+            &format!("file://./{name}.wasm"),
+            &["wasi:keyvalue/store@0.2.0-draft".to_string()],
+            &["wasi:http/incoming-handler@0.2.0".to_string()],
+            options,
+        )
+    }
+
+    #[test]
+    fn test_compose_http_components() {
+        let mut options = GenerateOptions::default();
+        let plans = vec![
+            http_component("api", &options),
+            http_component("ui", &options),
+        ];
+        let app = compose_application("shop", plans, &options).unwrap();
+
+        // Each component gets its own HTTP server on its own port, but they share keyvalue
+        let providers: Vec<&str> = app.providers.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            providers,
+            vec![
+                "wasi:keyvalue-target",
+                "api-wasi:http-source",
+                "ui-wasi:http-source"
+            ]
+        );
+        let addresses: Vec<&str> = app
+            .links
+            .iter()
+            .flat_map(|l| &l.source_config)
+            .filter_map(|c| c.properties.get("address"))
+            .map(String::as_str)
+            .collect();
+        assert_eq!(addresses, vec!["0.0.0.0:8000", "0.0.0.0:8001"]);
+
+        // With path routing one server is shared and each link carries its route
+        options.http_routing = HttpRouting::Path;
+        options
+            .http_routes
+            .insert("api".to_string(), "/api".to_string());
+        let plans = vec![
+            http_component("api", &options),
+            http_component("ui", &options),
+        ];
+        let app = compose_application("shop", plans, &options).unwrap();
+        assert_eq!(app.providers.len(), 2);
+        let routes: Vec<(Option<&str>, &str)> = app
+            .links
+            .iter()
+            .filter(|l| l.source == "wasi:http-source")
+            .map(|l| {
+                (
+                    l.name.as_deref(),
+                    l.source_config[0].properties["path"].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(routes, vec![(Some("api"), "/api"), (Some("ui"), "/ui")]);
+
+        // Two components can't share a route
+        options
+            .http_routes
+            .insert("ui".to_string(), "/api".to_string());
+        let plans = vec![
+            http_component("api", &options),
+            http_component("ui", &options),
+        ];
+        assert!(compose_application("shop", plans, &options).is_err());
+    }
+
+    #[test]
+    fn test_compose_provider_versions() {
+        // Components needing different images for one provider get a provider each, the second
+        // named after its version, and a third component shares the one with its image
+        let options = GenerateOptions::default();
+        let mut plans = vec![
+            http_component("api", &options),
+            http_component("worker", &options),
+            http_component("jobs", &options),
+        ];
+        for plan in &mut plans[1..] {
+            let keyvalue = plan
+                .providers
+                .iter_mut()
+                .find(|p| p.package == "keyvalue")
+                .unwrap();
+            keyvalue.image = "ghcr.io/acme/keyvalue:0.1.0".to_string();
+            keyvalue.version = Some("0.1.0".to_string());
+        }
+        let app = compose_application("shop", plans, &options).unwrap();
+        let providers: Vec<(&str, &str)> = app
+            .providers
+            .iter()
+            .filter(|p| p.package == "keyvalue")
+            .map(|p| (p.name.as_str(), p.image.as_str()))
+            .collect();
+        assert_eq!(
+            providers[1],
+            ("wasi:keyvalue-target-0.1.0", "ghcr.io/acme/keyvalue:0.1.0")
+        );
+        assert_eq!(providers.len(), 2);
+        let targets: Vec<(&str, &str)> = app
+            .links
+            .iter()
+            .filter(|l| l.package == "keyvalue")
+            .map(|l| (l.source.as_str(), l.target.as_str()))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("api", "wasi:keyvalue-target"),
+                ("worker", "wasi:keyvalue-target-0.1.0"),
+                ("jobs", "wasi:keyvalue-target-0.1.0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_server_names() {
+        // A split server named after its component can't take the name of another component
+        let mut options = GenerateOptions::default();
+        options.provider_name = Some("{package}".to_string());
+        let plans = vec![
+            http_component("api", &options),
+            http_component("api-http", &options),
+        ];
+        let app = compose_application("shop", plans, &options).unwrap();
+        let providers: Vec<&str> = app.providers.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(providers, vec!["keyvalue", "api-http-2", "api-http-http"]);
+        let servers: Vec<(&str, &str)> = app
+            .links
+            .iter()
+            .filter(|l| l.package == "http")
+            .map(|l| (l.source.as_str(), l.target.as_str()))
+            .collect();
+        assert_eq!(
+            servers,
+            vec![("api-http-2", "api"), ("api-http-http", "api-http")]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Explanations of how each import and export of a world was handled, to help track down why a
+//! manifest is missing a link or has a placeholder in it.
+
+use crate::plan::{ApplicationPlan, Classification, Direction, InterfaceDecision};
+
+const HEADERS: [&str; 6] = [
+    "INTERFACE",
+    "DIRECTION",
+    "DECISION",
+    "PROVIDER",
+    "IMAGE",
+    "REASON",
+];
+
+/// Renders the decision made for every import and export in the plan as a human readable table.
+/// Applications with several components get an extra column naming the component
+pub fn explain_table(plan: &ApplicationPlan) -> String {
+    let with_component = plan.components.len() > 1;
+    let row = |cells: Vec<String>, component: &str| -> Vec<String> {
+        if with_component {
+            std::iter::once(component.to_string())
+                .chain(cells)
+                .collect()
+        } else {
+            cells
+        }
+    };
+
+    let headers = row(HEADERS.map(ToString::to_string).to_vec(), "COMPONENT");
+    let rows: Vec<Vec<String>> = plan
+        .interfaces
+        .iter()
+        .map(|decision| row(explain_row(decision).to_vec(), &decision.component))
+        .collect();
+    render_table(headers, rows)
+}
+
+/// Renders rows of cells as a table with aligned columns
+pub(crate) fn render_table(headers: Vec<String>, rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(String::len).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    std::iter::once(&headers)
+        .chain(&rows)
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders the decision made for every import and export in the plan as pretty printed JSON
+pub fn explain_json(plan: &ApplicationPlan) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&plan.interfaces)
+}
+
+fn explain_row(decision: &InterfaceDecision) -> [String; 6] {
+    let (provider, image) = match &decision.classification {
+        Classification::Provider { provider, image }
+        | Classification::Custom { provider, image } => (provider.clone(), image.clone()),
+        _ => ("-".to_string(), "-".to_string()),
+    };
+    [
+        decision.interface.clone(),
+        match decision.direction {
+            Direction::Import => "import".to_string(),
+            Direction::Export => "export".to_string(),
+        },
+        decision.classification.label().to_string(),
+        provider,
+        image,
+        decision.reason.clone(),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{explain_json, explain_table};
+    use crate::compose::compose_application;
+    use crate::plan::{plan_application, ApplicationPlan, InterfaceDecision};
+    use crate::GenerateOptions;
+
+    fn plan(name: &str) -> ApplicationPlan {
+        plan_application(
+            name,
+            &format!("file://./{name}.wasm"),
+            &[
+                "wasi:io/streams@0.2.0".to_string(),
+                "wasi:keyvalue/store@0.2.0-draft".to_string(),
+            ],
+            &["wasi:http/incoming-handler@0.2.0".to_string()],
+            &GenerateOptions::default(),
+        )
+    }
+
+    #[test]
+    fn test_explain_table() {
+        let table = explain_table(&plan("echo"));
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("INTERFACE"));
+
+        // Columns are aligned, so every direction starts under its header
+        let direction = lines[0].find("DIRECTION").unwrap();
+        for line in &lines[1..] {
+            assert!(matches!(
+                &line[direction..direction + 6],
+                "import" | "export"
+            ));
+        }
+
+        let cells = |line: &str| {
+            line.split_whitespace()
+                .take(4)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(
+            cells(lines[1]),
+            "wasi:io/streams@0.2.0 import host-provided -"
+        );
+        assert_eq!(
+            cells(lines[2]),
+            "wasi:keyvalue/store@0.2.0-draft import provider wasi:keyvalue-target"
+        );
+        assert_eq!(
+            cells(lines[3]),
+            "wasi:http/incoming-handler@0.2.0 export provider wasi:http-source"
+        );
+
+        // Applications with several components name the component of each row
+        let options = GenerateOptions::default();
+        let app = compose_application("shop", vec![plan("api"), plan("ui")], &options).unwrap();
+        let table = explain_table(&app);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].starts_with("COMPONENT  INTERFACE"));
+        assert!(lines[4].starts_with("ui "));
+    }
+
+    #[test]
+    fn test_explain_json() {
+        let plan = plan("echo");
+        let json = explain_json(&plan).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["classification"]["kind"], "host-provided");
+        assert_eq!(value[1]["direction"], "import");
+        assert_eq!(value[1]["classification"]["kind"], "provider");
+        assert_eq!(
+            value[1]["classification"]["provider"],
+            "wasi:keyvalue-target"
+        );
+        assert_eq!(value[2]["direction"], "export");
+
+        let decisions: Vec<InterfaceDecision> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decisions, plan.interfaces);
+    }
+}
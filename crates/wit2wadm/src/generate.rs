@@ -0,0 +1,789 @@
+//! The pipeline behind `generate`, `inspect` and `check`, shared by the CLI and the wash plugin.
+//! Everything that touches the filesystem or the terminal goes through [Io], so each of them only
+//! implements how it reads and writes files.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use wadm::model::Manifest;
+use wit_parser::Resolve;
+
+use crate::batch::{
+    component_name, convert_artifact, is_component_file, matches_pattern, BatchResult,
+};
+use crate::check::{check_manifest, parse_manifest};
+use crate::cli::Args;
+use crate::compose::compose_application;
+use crate::lockfile::{Lockfile, LOCKFILE_NAME};
+use crate::oci::{self, ComponentArtifact};
+use crate::project::{Project, ProjectInput, PROJECT_FILE};
+use crate::render::{manifest_file_name, render_manifests, OutputFormat};
+use crate::settings::{Settings, SETTINGS_FILE};
+use crate::shared::{mark_shared, shared_providers, SHARED_DESCRIPTION};
+use crate::strict::check_strict;
+use crate::{find_world, plan_component, plan_to_wadm, plan_wasm, plan_world, GenerateOptions};
+
+/// The input path that reads the component from stdin
+pub const STDIN: &str = "-";
+
+/// How [generate] reads inputs and writes its output. Reading only takes `&self`, so components
+/// of a batch can be read from several threads at once
+pub trait Io: Sync {
+    /// Whether a file or directory exists and can be read
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Whether the path is a directory
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Reads a file
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>>;
+
+    /// Lists the paths of the entries of a directory, in any order
+    fn read_dir(&self, path: &Path) -> anyhow::Result<Vec<PathBuf>>;
+
+    /// Reads everything written to stdin
+    fn read_stdin(&mut self) -> anyhow::Result<Vec<u8>>;
+
+    /// Loads a WIT directory and its dependencies
+    fn read_wit(&self, path: &Path) -> anyhow::Result<Resolve>;
+
+    /// The directory the project in `wasmcloud.toml` is looked for in when no input is given
+    fn current_dir(&self) -> anyhow::Result<PathBuf>;
+
+    /// Makes sure a directory exists before files are written to it
+    fn create_dir_all(&mut self, path: &Path) -> anyhow::Result<()>;
+
+    /// Writes a file. An existing file is only replaced when `overwrite` is set
+    fn write(&mut self, path: &Path, contents: &[u8], overwrite: bool) -> anyhow::Result<()>;
+
+    /// Prints output, like a manifest, to stdout followed by a newline
+    fn print(&mut self, output: &str);
+
+    /// Prints a message, like a warning, to stderr
+    fn log(&mut self, message: &str);
+
+    /// Whether the path is a directory holding an OCI image layout
+    fn is_oci_layout(&self, path: &Path) -> bool {
+        self.is_dir(path) && oci::is_oci_layout_in(path, |path| self.exists(path))
+    }
+
+    /// Whether the input is a WIT directory rather than a component
+    fn is_wit_dir(&self, path: &Path) -> bool {
+        self.is_dir(path) && !self.is_oci_layout(path)
+    }
+
+    /// Reads a component from a file or an OCI image layout directory, which may be an OCI
+    /// artifact (see [oci::read_component])
+    fn read_component(&self, path: &Path) -> anyhow::Result<ComponentArtifact> {
+        if self.is_oci_layout(path) {
+            return oci::read_layout(path, |path| self.read(path));
+        }
+        oci::read_component_bytes(self.read(path)?)
+    }
+}
+
+/// What [generate] produced
+#[derive(Debug, Default)]
+pub struct Generated {
+    /// The manifests that were written or printed. `check` and `--explain` don't produce any
+    pub manifests: Vec<Manifest>,
+    /// The warnings found while planning, which were logged as well
+    pub warnings: Vec<String>,
+}
+
+/// Generates manifests for the inputs given by `args` and writes them out, or checks an existing
+/// manifest against what would be generated when `check` is given. Settings, lockfile and config
+/// files are loaded from the paths in `args` or their defaults in the current directory
+pub fn generate(
+    mut args: Args,
+    check: Option<PathBuf>,
+    io: &mut impl Io,
+) -> anyhow::Result<Generated> {
+    let settings_path = args
+        .settings
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(SETTINGS_FILE));
+    let mut options = if args.settings.is_some() || io.exists(&settings_path) {
+        let mut settings = Settings::parse(&read_to_string(io, &settings_path)?)
+            .with_context(|| format!("failed to parse {}", settings_path.display()))?;
+        settings.resolve_paths(settings_path.parent().unwrap_or(Path::new("")));
+        args.apply_settings(settings)
+    } else {
+        args.generate_options()
+    };
+
+    if let Some(path) = &args.provider_config_file {
+        options
+            .add_provider_config_file(&read_to_string(io, path)?)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+    }
+
+    if let Some(path) = &args.config_file {
+        options
+            .add_config_file(&read_to_string(io, path)?)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+    }
+
+    let lockfile_path = args
+        .lockfile
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(LOCKFILE_NAME));
+    if args.lockfile.is_some() || io.exists(&lockfile_path) {
+        let lockfile = Lockfile::parse(&read_to_string(io, &lockfile_path)?)
+            .with_context(|| format!("failed to parse {}", lockfile_path.display()))?;
+        options.lockfile = Some(lockfile);
+    }
+
+    // Without an input, convert what the project in the current directory builds
+    let project = if args.wit_folder_or_component.is_none() && args.batch.is_empty() {
+        let dir = io.current_dir()?;
+        let contents = read_to_string(io, &dir.join(PROJECT_FILE))
+            .with_context(|| format!("no input given and no {PROJECT_FILE} found"))?;
+        Some((dir, Project::parse(&contents)?))
+    } else {
+        None
+    };
+    let (name, version) = match &project {
+        Some((_, project)) => (
+            args.app_name.or_else(|| project.name.clone()),
+            args.app_version.or_else(|| project.version.clone()),
+        ),
+        None => (args.app_name, args.app_version),
+    };
+    let name = name.unwrap_or_else(|| "wit2wadm".to_string());
+    let description = args
+        .app_description
+        .unwrap_or_else(|| "A wasmCloud Application".to_string());
+    let version = version.unwrap_or_else(|| "v0.1.0".to_string());
+    let format = args.format.unwrap_or_default();
+    let strict = args.strict.unwrap_or_default();
+
+    if !args.batch.is_empty() {
+        let inputs = collect_inputs(io, &args.batch).context("failed to find components")?;
+        let results = convert_components(io, &inputs, &description, &version, &options);
+        return write_batch(
+            io,
+            results,
+            format,
+            args.out_dir.as_deref(),
+            args.output.as_deref(),
+            args.force,
+            strict,
+        );
+    }
+
+    let (input, world_name) = match (args.wit_folder_or_component, project) {
+        (Some(input), _) => (input, args.world_name),
+        (None, project) => {
+            let (dir, project) = project.context("a WIT folder or component is required")?;
+            match project
+                .input(&dir, |path| io.exists(path))
+                .context("failed to find the project's component")?
+            {
+                ProjectInput::Component(path) => (path, args.world_name),
+                ProjectInput::Wit { folder, world } => (folder, args.world_name.or(Some(world))),
+            }
+        }
+    };
+    let default_image = format!("myregistry.io/{name}:{version}");
+    let plan = if input == Path::new(STDIN) {
+        let bytes = io
+            .read_stdin()
+            .context("failed to read component from stdin")?;
+        let artifact = oci::read_component_bytes(bytes)?;
+        plan_wasm(
+            &artifact.wasm,
+            world_name.as_deref(),
+            &name,
+            args.app_image.or(artifact.image).unwrap_or(default_image),
+            &options,
+        )
+    } else if io.is_wit_dir(&input) {
+        let world_name =
+            world_name.context("world name is required when providing a WIT folder")?;
+        let resolve = io
+            .read_wit(&input)
+            .with_context(|| format!("failed to load WIT from {}", input.display()))?;
+        find_world(&resolve, &world_name).map(|world| {
+            plan_world(
+                &resolve,
+                world,
+                &name,
+                args.app_image.unwrap_or(default_image),
+                &options,
+            )
+        })
+    } else {
+        let artifact = io
+            .read_component(&input)
+            .with_context(|| format!("failed to read {}", input.display()))?;
+        plan_wasm(
+            &artifact.wasm,
+            world_name.as_deref(),
+            &name,
+            args.app_image
+                .or(artifact.image)
+                .unwrap_or_else(|| file_image(&input)),
+            &options,
+        )
+    }
+    .context("failed to convert WIT to WADM")?;
+
+    let plan = if args.with.is_empty() {
+        plan
+    } else {
+        let mut plans = vec![plan];
+        for path in &args.with {
+            let artifact = io
+                .read_component(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let component_name = component_name(path, artifact.image.as_deref())?;
+            let image = artifact.image.unwrap_or_else(|| file_image(path));
+            plans.push(
+                plan_component(&artifact.wasm, &component_name, image, &options)
+                    .with_context(|| format!("failed to convert {}", path.display()))?,
+            );
+        }
+        compose_application(&name, plans, &options)
+            .context("failed to combine components into one application")?
+    };
+
+    for warning in &plan.warnings {
+        io.log(&format!("warning: {warning}"));
+    }
+    let mut warnings = plan.warnings.clone();
+
+    if let Some(path) = check {
+        let manifest = parse_manifest(&read_to_string(io, &path)?)?;
+        check_manifest(&manifest, &plan)?;
+        io.log(&format!("{} matches the component", path.display()));
+        return Ok(Generated {
+            manifests: Vec::new(),
+            warnings,
+        });
+    }
+
+    if let Some(format) = args.explain {
+        let explanation = format.render(&plan).context("failed to explain manifest")?;
+        io.print(&explanation);
+        return Ok(Generated {
+            manifests: Vec::new(),
+            warnings,
+        });
+    }
+
+    let manifest = plan_to_wadm(&plan, &description, &version);
+    if strict {
+        check_strict(&manifest)?;
+    }
+
+    // The shared application's providers are copies of the application's, which strict mode
+    // has already checked, and nothing in it links to them
+    let mut manifests = vec![manifest];
+    if let Some(shared_app) = &args.emit_shared {
+        let shared = shared_providers(&plan, shared_app, &args.shared_packages);
+        if shared.providers.is_empty() {
+            let warning = format!("{name} uses no well known providers to put in {shared_app}");
+            io.log(&format!("warning: {warning}"));
+            warnings.push(warning);
+        } else {
+            let mut manifest = plan_to_wadm(&shared, SHARED_DESCRIPTION, &version);
+            mark_shared(&mut manifest);
+            manifests.push(manifest);
+        }
+    }
+    write_manifests(
+        io,
+        &manifests,
+        format,
+        args.out_dir.as_deref(),
+        args.output.as_deref(),
+        args.force,
+    )?;
+    Ok(Generated {
+        manifests,
+        warnings,
+    })
+}
+
+/// Expands the paths given for a batch into the components to convert. Directories contribute
+/// every `.wasm` file and OCI artifact directly inside them, and a `*` in the last part of a path
+/// matches any characters in file names, e.g. `build/*_s.wasm`. Other paths are taken as they are
+fn collect_inputs(io: &impl Io, paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        let pattern = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .filter(|name| name.contains('*'));
+        if let Some(pattern) = pattern {
+            let dir = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            let matched = read_dir_sorted(io, dir)?
+                .into_iter()
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .is_some_and(|name| matches_pattern(&pattern, &name.to_string_lossy()))
+                })
+                .collect::<Vec<_>>();
+            if matched.is_empty() {
+                bail!("no files match {}", path.display());
+            }
+            inputs.extend(matched);
+        } else if io.is_dir(path) && !io.is_oci_layout(path) {
+            inputs.extend(read_dir_sorted(io, path)?.into_iter().filter(|entry| {
+                (is_component_file(entry) && !io.is_dir(entry)) || io.is_oci_layout(entry)
+            }));
+        } else {
+            inputs.push(path.clone());
+        }
+    }
+    Ok(inputs)
+}
+
+fn read_dir_sorted(io: &impl Io, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut entries = io
+        .read_dir(dir)
+        .with_context(|| format!("failed to read directory {}", dir.display()))?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Converts every component into its own application, spread over the available threads, and
+/// returns the results in the order the components were given. A component that fails to read or
+/// convert doesn't stop the others
+fn convert_components(
+    io: &impl Io,
+    inputs: &[PathBuf],
+    description: &str,
+    default_version: &str,
+    options: &GenerateOptions,
+) -> Vec<BatchResult> {
+    let convert = |path: &PathBuf| BatchResult {
+        path: path.clone(),
+        result: io
+            .read_component(path)
+            .and_then(|artifact| {
+                convert_artifact(path, artifact, description, default_version, options)
+            })
+            .with_context(|| format!("failed to convert {}", path.display())),
+    };
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    // Without threads, like in the wash plugin, the components are converted one at a time
+    if threads == 1 || inputs.len() <= 1 {
+        return inputs.iter().map(convert).collect();
+    }
+    let chunk_size = inputs.len().div_ceil(threads);
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = inputs
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(convert).collect::<Vec<_>>()))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("batch worker should not panic"))
+            .collect()
+    })
+}
+
+/// Writes the manifests converted in a batch to their own files in the output directory, or all
+/// together to the output file or stdout, then logs a summary. Fails if any component failed
+fn write_batch(
+    io: &mut impl Io,
+    results: Vec<BatchResult>,
+    format: OutputFormat,
+    out_dir: Option<&Path>,
+    output: Option<&Path>,
+    force: bool,
+    strict: bool,
+) -> anyhow::Result<Generated> {
+    let total = results.len();
+    let mut generated = Generated::default();
+    let mut failures = Vec::new();
+    for BatchResult { path, result } in results {
+        let written = result.and_then(|(plan, manifest)| {
+            for warning in &plan.warnings {
+                let warning = format!("{}: {warning}", path.display());
+                io.log(&format!("warning: {warning}"));
+                generated.warnings.push(warning);
+            }
+            if strict {
+                check_strict(&manifest)?;
+            }
+            if out_dir.is_some() {
+                write_manifests(
+                    io,
+                    std::slice::from_ref(&manifest),
+                    format,
+                    out_dir,
+                    None,
+                    force,
+                )?;
+            }
+            generated.manifests.push(manifest);
+            Ok(())
+        });
+        if let Err(err) = written {
+            failures.push((path, err));
+        }
+    }
+
+    if out_dir.is_none() && !generated.manifests.is_empty() {
+        write_manifests(io, &generated.manifests, format, None, output, force)
+            .context("failed to write manifests")?;
+    }
+
+    io.log(&format!(
+        "Converted {} of {total} components",
+        total - failures.len()
+    ));
+    for (path, err) in &failures {
+        io.log(&format!("error: {}: {err:#}", path.display()));
+    }
+    if !failures.is_empty() {
+        bail!("{} of {total} components failed to convert", failures.len());
+    }
+    Ok(generated)
+}
+
+/// Writes each manifest to its own file in the output directory, or all of them together to the
+/// output file or stdout
+fn write_manifests(
+    io: &mut impl Io,
+    manifests: &[Manifest],
+    format: OutputFormat,
+    out_dir: Option<&Path>,
+    output: Option<&Path>,
+    force: bool,
+) -> anyhow::Result<()> {
+    if let Some(out_dir) = out_dir {
+        io.create_dir_all(out_dir)
+            .context("failed to create output directory")?;
+        for manifest in manifests {
+            let path = out_dir.join(manifest_file_name(manifest, format));
+            let rendered = render_manifests(std::slice::from_ref(manifest), format)?;
+            io.write(&path, with_newline(rendered).as_bytes(), force)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            io.log(&format!("Wrote {}", path.display()));
+        }
+        return Ok(());
+    }
+
+    let rendered = render_manifests(manifests, format).context("failed to render manifest")?;
+    match output {
+        Some(path) => io
+            .write(path, with_newline(rendered).as_bytes(), force)
+            .with_context(|| format!("failed to write {}", path.display())),
+        None => {
+            io.print(&rendered);
+            Ok(())
+        }
+    }
+}
+
+fn read_to_string(io: &impl Io, path: &Path) -> anyhow::Result<String> {
+    let contents = io
+        .read(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    String::from_utf8(contents).with_context(|| format!("{} is not valid UTF-8", path.display()))
+}
+
+/// The image of a component that was only given as a file
+fn file_image(path: &Path) -> String {
+    format!("file://./{}", path.to_string_lossy())
+}
+
+/// Makes sure a file written to disk ends with a newline
+fn with_newline(mut output: String) -> String {
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::path::{Path, PathBuf};
+
+    use anyhow::{bail, Context as _};
+    use clap::Parser;
+    use wit_parser::{Resolve, UnresolvedPackage};
+
+    use super::{generate, Generated, Io};
+    use crate::cli::{Cli, Command};
+    use crate::test_util::{component, TEST_WIT};
+
+    /// Files, stdin and output kept in memory
+    #[derive(Default)]
+    struct MemoryIo {
+        files: BTreeMap<PathBuf, Vec<u8>>,
+        /// WIT directories and the contents of their single file
+        wit: BTreeMap<PathBuf, String>,
+        stdin: Vec<u8>,
+        stdout: Vec<String>,
+        stderr: Vec<String>,
+    }
+
+    impl Io for MemoryIo {
+        fn exists(&self, path: &Path) -> bool {
+            self.files.contains_key(path) || self.is_dir(path)
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.wit.contains_key(path)
+                || self
+                    .files
+                    .keys()
+                    .any(|file| file != path && file.starts_with(path))
+        }
+
+        fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+            self.files
+                .get(path)
+                .cloned()
+                .with_context(|| format!("{} not found", path.display()))
+        }
+
+        fn read_dir(&self, path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+            let entries: BTreeSet<PathBuf> = self
+                .files
+                .keys()
+                .filter_map(|file| file.strip_prefix(path).ok()?.components().next())
+                .map(|entry| path.join(entry))
+                .collect();
+            Ok(entries.into_iter().collect())
+        }
+
+        fn read_stdin(&mut self) -> anyhow::Result<Vec<u8>> {
+            Ok(std::mem::take(&mut self.stdin))
+        }
+
+        fn read_wit(&self, path: &Path) -> anyhow::Result<Resolve> {
+            let mut resolve = Resolve::new();
+            resolve.push(UnresolvedPackage::parse(
+                &path.join("world.wit"),
+                &self.wit[path],
+            )?)?;
+            Ok(resolve)
+        }
+
+        fn current_dir(&self) -> anyhow::Result<PathBuf> {
+            Ok(PathBuf::new())
+        }
+
+        fn create_dir_all(&mut self, _path: &Path) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn write(&mut self, path: &Path, contents: &[u8], overwrite: bool) -> anyhow::Result<()> {
+            if !overwrite && self.files.contains_key(path) {
+                bail!("{} already exists", path.display());
+            }
+            self.files.insert(path.to_path_buf(), contents.to_vec());
+            Ok(())
+        }
+
+        fn print(&mut self, output: &str) {
+            self.stdout.push(output.to_string());
+        }
+
+        fn log(&mut self, message: &str) {
+            self.stderr.push(message.to_string());
+        }
+    }
+
+    impl MemoryIo {
+        fn file(&self, path: &str) -> String {
+            String::from_utf8(self.files[Path::new(path)].clone()).unwrap()
+        }
+    }
+
+    /// Runs a command the way the CLI and the plugin do
+    fn run(io: &mut MemoryIo, args: &[&str]) -> anyhow::Result<Generated> {
+        let cli = Cli::try_parse_from([&["wit2wadm"][..], args].concat())?;
+        match cli.into_command() {
+            Command::Generate(args) => generate(args, None, io),
+            Command::Check(args) => generate(args.generate, Some(args.manifest), io),
+            _ => bail!("unexpected command"),
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let mut io = MemoryIo {
+            stdin: component(),
+            ..Default::default()
+        };
+        io.files.insert("echo.wasm".into(), component());
+
+        // Components are read from stdin too, and printed without an output
+        let generated = run(&mut io, &["-", "--name", "echo"]).unwrap();
+        assert_eq!(generated.manifests.len(), 1);
+        assert_eq!(generated.manifests[0].metadata.name, "echo");
+        assert_eq!(io.stdout.len(), 1);
+        assert!(io.stdout[0].contains("myregistry.io/echo:v0.1.0"));
+        for warning in &generated.warnings {
+            assert!(io.stderr.contains(&format!("warning: {warning}")));
+        }
+
+        // Files in the output directory end with a newline, like the output file
+        run(&mut io, &["echo.wasm", "--out-dir", "manifests"]).unwrap();
+        let written = io.file("manifests/wit2wadm.yaml");
+        assert!(written.ends_with('\n'), "{written}");
+        assert!(written.contains("file://./echo.wasm"));
+        assert!(run(&mut io, &["echo.wasm", "--out-dir", "manifests"]).is_err());
+        run(&mut io, &["echo.wasm", "--out-dir", "manifests", "--force"]).unwrap();
+
+        run(
+            &mut io,
+            &["echo.wasm", "-o", "echo.yaml", "--format", "json"],
+        )
+        .unwrap();
+        assert!(io.file("echo.yaml").ends_with("}\n"));
+
+        // The manifest that was just written matches, while a missing one is an error
+        run(&mut io, &["check", "echo.wasm", "--manifest", "echo.yaml"]).unwrap();
+        assert_eq!(io.stderr.last().unwrap(), "echo.yaml matches the component");
+        assert!(run(
+            &mut io,
+            &["check", "echo.wasm", "--manifest", "missing.yaml"]
+        )
+        .is_err());
+
+        // A component without well known providers has nothing to share
+        let generated = run(&mut io, &["echo.wasm", "--emit-shared", "providers"]).unwrap();
+        assert_eq!(generated.manifests.len(), 1);
+        assert!(generated.warnings.iter().any(|w| w.contains("providers")));
+
+        // OCI image layout directories are read through the same I/O, not as WIT folders
+        let manifest = serde_json::json!({
+            "layers": [{
+                "mediaType": "application/vnd.module.wasm.content.layer.v1+wasm",
+                "digest": "sha256:layer0",
+            }]
+        });
+        let index = serde_json::json!({
+            "manifests": [{
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": "sha256:1111",
+                "annotations": { "org.opencontainers.image.ref.name": "ghcr.io/acme/echo:0.1.0" },
+            }]
+        });
+        for (name, contents) in [
+            ("oci-layout", br#"{"imageLayoutVersion": "1.0.0"}"#.to_vec()),
+            ("index.json", serde_json::to_vec(&index).unwrap()),
+            ("blobs/sha256/1111", serde_json::to_vec(&manifest).unwrap()),
+            ("blobs/sha256/layer0", component()),
+        ] {
+            io.files.insert(Path::new("echo").join(name), contents);
+        }
+        run(&mut io, &["echo", "--name", "echo"]).unwrap();
+        assert!(io
+            .stdout
+            .last()
+            .unwrap()
+            .contains("ghcr.io/acme/echo:0.1.0@sha256:1111"));
+    }
+
+    #[test]
+    fn test_generate_batch() {
+        let mut io = MemoryIo::default();
+        io.files.insert("build/api.wasm".into(), component());
+        io.files
+            .insert("build/broken.wasm".into(), b"not a component".to_vec());
+        io.files.insert("build/echo.wasm".into(), component());
+        io.files
+            .insert("build/README.md".into(), b"# Components".to_vec());
+
+        // A component that fails to convert doesn't stop the others, but fails the batch
+        let err = run(&mut io, &["--batch", "build", "--out-dir", "manifests"]).unwrap_err();
+        assert_eq!(err.to_string(), "1 of 3 components failed to convert");
+        assert!(io.file("manifests/api.yaml").ends_with('\n'));
+        assert!(io
+            .file("manifests/echo.yaml")
+            .contains("file://./build/echo.wasm"));
+        assert!(!io.exists(Path::new("manifests/broken.yaml")));
+        assert!(io
+            .stderr
+            .contains(&"Converted 2 of 3 components".to_string()));
+        assert!(io.stderr.iter().any(|line| line
+            .starts_with("error: build/broken.wasm: failed to convert build/broken.wasm")));
+
+        // Without an output directory the manifests are printed together, in the order given
+        let generated = run(&mut io, &["--batch", "build/echo.wasm", "build/api.wasm"]).unwrap();
+        let names: Vec<&str> = generated
+            .manifests
+            .iter()
+            .map(|manifest| manifest.metadata.name.as_str())
+            .collect();
+        assert_eq!(names, ["echo", "api"]);
+        let printed = io.stdout.last().unwrap();
+        assert_eq!(printed.matches("---\n").count(), 1);
+        assert!(printed.find("name: echo").unwrap() < printed.find("name: api").unwrap());
+        assert_eq!(io.stderr.last().unwrap(), "Converted 2 of 2 components");
+
+        // Patterns match file names in a directory
+        let generated = run(&mut io, &["--batch", "build/e*.wasm"]).unwrap();
+        assert_eq!(generated.manifests.len(), 1);
+        assert!(run(&mut io, &["--batch", "build/*.tar.gz"]).is_err());
+    }
+
+    #[test]
+    fn test_generate_defaults() {
+        let mut io = MemoryIo::default();
+
+        // Without an input, the project in the current directory is converted
+        assert!(run(&mut io, &[]).is_err());
+        io.files.insert(
+            "wasmcloud.toml".into(),
+            b"name = \"echo\"\nversion = \"0.2.0\"\n\n[component]\nwit_world = \"server\"\n"
+                .to_vec(),
+        );
+        io.wit.insert("wit".into(), TEST_WIT.to_string());
+        let generated = run(&mut io, &[]).unwrap();
+        assert_eq!(generated.manifests[0].metadata.name, "echo");
+        assert!(io.stdout[0].contains("myregistry.io/echo:0.2.0"));
+
+        // The settings file in the current directory is applied under the flags
+        io.files.insert(
+            "wit2wadm.toml".into(),
+            b"name = \"settings\"\nstrict = true\n".to_vec(),
+        );
+        // Strict mode fails on the placeholder application image
+        assert!(run(&mut io, &[]).is_err());
+        let generated = run(&mut io, &["--strict=false", "--name", "flag"]).unwrap();
+        assert_eq!(generated.manifests[0].metadata.name, "flag");
+
+        io.files
+            .insert("wit2wadm.toml".into(), b"typo = 1\n".to_vec());
+        assert!(run(&mut io, &[]).is_err());
+        io.files.remove(Path::new("wit2wadm.toml"));
+
+        // Paths in a settings file are relative to the file, not the current directory
+        io.files.insert(
+            "conf/wit2wadm.toml".into(),
+            b"config-file = \"config.toml\"\nlockfile = \"wit2wadm.lock\"\n".to_vec(),
+        );
+        io.files
+            .insert("conf/config.toml".into(), b"greeting = \"hi\"\n".to_vec());
+        io.files.insert("conf/wit2wadm.lock".into(), Vec::new());
+        let generated = run(&mut io, &["--settings", "conf/wit2wadm.toml"]).unwrap();
+        assert!(generated
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("doesn't import wasi:config")));
+        io.files.remove(Path::new("conf/config.toml"));
+        let err = run(&mut io, &["--settings", "conf/wit2wadm.toml"]).unwrap_err();
+        assert!(format!("{err:#}").contains("conf/config.toml"), "{err:#}");
+
+        // An explicit lockfile has to exist, while the default one is optional
+        assert!(run(&mut io, &["--lockfile", "missing.lock"]).is_err());
+    }
+}
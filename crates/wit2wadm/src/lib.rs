@@ -1,11 +1,36 @@
+pub mod batch;
+pub mod catalog;
+pub mod check;
 #[cfg(feature = "cli")]
 pub mod cli;
+pub mod compose;
+pub mod explain;
+#[cfg(feature = "cli")]
+pub mod generate;
 mod interface;
+pub mod lockfile;
 mod manifest;
+pub mod naming;
+pub mod oci;
+mod options;
+pub mod plan;
+pub mod project;
+pub mod render;
+pub mod scan;
+pub mod settings;
+pub mod shared;
+pub mod strict;
+#[cfg(test)]
+mod test_util;
 
 use std::path::Path;
 
 use anyhow::{bail, Context as _};
+#[cfg(feature = "cli")]
+pub use generate::generate;
+pub use options::{GenerateOptions, Subscription};
+use plan::ApplicationPlan;
+use scan::{ComponentInterfaces, WasmKind};
 use wadm::model::Manifest;
 use wit_parser::{Resolve, UnresolvedPackage, World};
 
@@ -18,24 +43,15 @@ pub fn wit2wadm_from_folder(
     version: impl AsRef<str>,
     image: impl AsRef<str>,
 ) -> anyhow::Result<Manifest> {
-    let mut resolve = Resolve::new();
-
-    resolve
-        .push_path(wit_folder)
-        .context("should be able to load wits")?;
-
-    let world = resolve
-        .worlds
-        .iter()
-        .find_map(|(_, w)| (w.name == world_name.as_ref()).then_some(w))
-        .cloned()
-        .context("component world missing")
-        .expect("should be able to find component world");
-
-    let manifest = wit2wadm(resolve, &world, name, description, version, image)
-        .context("should be able to convert to manifest")?;
-
-    Ok(manifest)
+    let plan = plan_folder(
+        wit_folder,
+        world_name,
+        name,
+        image,
+        &GenerateOptions::default(),
+    )
+    .context("should be able to convert to manifest")?;
+    Ok(plan_to_wadm(&plan, description, version))
 }
 
 /// Takes a vec of UnresolvedPackages (from wit) and converts it to a wadm application manifest. The
@@ -49,26 +65,18 @@ pub fn raw_wit_to_wadm(
     version: impl AsRef<str>,
     image: impl AsRef<str>,
 ) -> anyhow::Result<Manifest> {
-    let mut resolve = Resolve::new();
-
-    for package in packages {
-        resolve
-            .push(package)
-            .context("failed to load wit document")?;
-    }
-
-    let world = resolve
-        .worlds
-        .iter()
-        .find_map(|(_, w)| (w.name == world_name.as_ref()).then_some(w))
-        .cloned()
-        .context("component world missing")
-        .expect("should be able to find component world");
-
-    wit2wadm(resolve, &world, name, description, version, image)
+    let plan = plan_raw_wit(
+        packages,
+        world_name,
+        name,
+        image,
+        &GenerateOptions::default(),
+    )?;
+    Ok(plan_to_wadm(&plan, description, version))
 }
 
-/// Loads a WIT component from a file and converts it to a wadm application manifest
+/// Loads a WIT component from a file and converts it to a wadm application manifest. The file
+/// may be a raw component, an OCI image layout directory or a tarball of one, see [oci]
 pub fn wit2wadm_from_component(
     wit_component: impl AsRef<Path>,
     name: impl AsRef<str>,
@@ -76,8 +84,8 @@ pub fn wit2wadm_from_component(
     version: impl AsRef<str>,
     image: impl AsRef<str>,
 ) -> anyhow::Result<Manifest> {
-    let wasm = std::fs::read(&wit_component).context("failed to read WIT component")?;
-    raw_component_to_wadm(wasm, name, description, version, image)
+    let artifact = oci::read_component(&wit_component)?;
+    raw_component_to_wadm(artifact.wasm, name, description, version, image)
         .context("should be able to convert to manifest")
 }
 
@@ -90,23 +98,8 @@ pub fn raw_component_to_wadm(
     version: impl AsRef<str>,
     image: impl AsRef<str>,
 ) -> anyhow::Result<Manifest> {
-    let (resolve, world) =
-        match wit_component::decode(&component).context("failed to decode WIT component")? {
-            wit_component::DecodedWasm::Component(resolve, world) => (resolve, world),
-            wit_component::DecodedWasm::WitPackage(..) => {
-                bail!("binary-encoded WIT packages not currently supported")
-            }
-        };
-
-    let world = resolve
-        .worlds
-        .iter()
-        .find_map(|(id, w)| (id == world).then_some(w))
-        .cloned()
-        .context("component world missing")
-        .expect("should be able to find component world");
-
-    wit2wadm(resolve, &world, name, description, version, image)
+    let plan = plan_component(&component, name, image, &GenerateOptions::default())?;
+    Ok(plan_to_wadm(&plan, description, version))
 }
 
 /// Converts a component [Resolve] and [World] into a wadm application manifest
@@ -118,24 +111,221 @@ pub fn wit2wadm(
     version: impl AsRef<str>,
     image: impl AsRef<str>,
 ) -> anyhow::Result<Manifest> {
+    let plan = plan_world(&resolve, world, name, image, &GenerateOptions::default());
+    Ok(plan_to_wadm(&plan, description, version))
+}
+
+/// Plans the wadm application for the raw bytes of a component without rendering a manifest.
+/// Binary-encoded WIT packages with a single world and core modules are accepted as well, see
+/// [plan_wasm]
+///
+/// Only the outer import and export sections are read when possible (see [scan]), falling back
+/// to fully decoding the component otherwise
+pub fn plan_component(
+    component: &[u8],
+    name: impl AsRef<str>,
+    image: impl AsRef<str>,
+    options: &GenerateOptions,
+) -> anyhow::Result<ApplicationPlan> {
+    plan_wasm(component, None, name, image, options)
+}
+
+/// Plans the wadm application for the raw bytes of a component, a binary-encoded WIT package or
+/// a core module built for a component, telling them apart by their contents (see
+/// [scan::sniff]). `world_name` selects the world of a WIT package with several worlds, while
+/// core modules are planned from the world embedded in them when they were built
+pub fn plan_wasm(
+    wasm: &[u8],
+    world_name: Option<&str>,
+    name: impl AsRef<str>,
+    image: impl AsRef<str>,
+    options: &GenerateOptions,
+) -> anyhow::Result<ApplicationPlan> {
+    let ComponentInterfaces { imports, exports } = match scan::sniff(wasm)? {
+        WasmKind::Component(interfaces) => interfaces,
+        // Components that only export types, and no imports, are indistinguishable from WIT
+        // packages without decoding them
+        WasmKind::WitPackage => {
+            match wit_component::decode(wasm).context("failed to decode WIT package")? {
+                wit_component::DecodedWasm::WitPackage(resolve, package) => {
+                    let world = resolve
+                        .select_world(package, world_name)
+                        .context("failed to select a world from the WIT package")?;
+                    world_interfaces(&resolve, &resolve.worlds[world])
+                }
+                wit_component::DecodedWasm::Component(resolve, world) => {
+                    world_interfaces(&resolve, &resolve.worlds[world])
+                }
+            }
+        }
+        WasmKind::CoreModule => {
+            let (_, bindgen) = wit_component::metadata::decode(wasm)
+                .context("failed to read the WIT world embedded in the core module")?;
+            let interfaces =
+                world_interfaces(&bindgen.resolve, &bindgen.resolve.worlds[bindgen.world]);
+            if interfaces.imports.is_empty() && interfaces.exports.is_empty() {
+                bail!("core module has no WIT world embedded, it may not be built for a component");
+            }
+            interfaces
+        }
+    };
+    Ok(plan::plan_application(
+        name.as_ref(),
+        image.as_ref(),
+        &imports,
+        &exports,
+        options,
+    ))
+}
+
+/// Plans the wadm application for a WIT world and deps loaded from a folder without rendering a
+/// manifest
+pub fn plan_folder(
+    wit_folder: impl AsRef<Path>,
+    world_name: impl AsRef<str>,
+    name: impl AsRef<str>,
+    image: impl AsRef<str>,
+    options: &GenerateOptions,
+) -> anyhow::Result<ApplicationPlan> {
+    let mut resolve = Resolve::new();
+
+    resolve
+        .push_path(wit_folder)
+        .context("should be able to load wits")?;
+
+    let world = find_world(&resolve, world_name.as_ref())?;
+    Ok(plan_world(&resolve, world, name, image, options))
+}
+
+/// Plans the wadm application for a vec of UnresolvedPackages (from wit) without rendering a
+/// manifest. The vec of unresolved packages should be ordered properly
+pub fn plan_raw_wit(
+    packages: Vec<UnresolvedPackage>,
+    world_name: impl AsRef<str>,
+    name: impl AsRef<str>,
+    image: impl AsRef<str>,
+    options: &GenerateOptions,
+) -> anyhow::Result<ApplicationPlan> {
+    let mut resolve = Resolve::new();
+
+    for package in packages {
+        resolve
+            .push(package)
+            .context("failed to load wit document")?;
+    }
+
+    let world = find_world(&resolve, world_name.as_ref())?;
+    Ok(plan_world(&resolve, world, name, image, options))
+}
+
+/// Plans the wadm application for a component [Resolve] and [World] without rendering a manifest
+pub fn plan_world(
+    resolve: &Resolve,
+    world: &World,
+    name: impl AsRef<str>,
+    image: impl AsRef<str>,
+    options: &GenerateOptions,
+) -> ApplicationPlan {
+    let ComponentInterfaces { imports, exports } = world_interfaces(resolve, world);
+    plan::plan_application(name.as_ref(), image.as_ref(), &imports, &exports, options)
+}
+
+/// Renders an [ApplicationPlan] into a wadm application manifest
+pub fn plan_to_wadm(
+    plan: &ApplicationPlan,
+    description: impl AsRef<str>,
+    version: impl AsRef<str>,
+) -> Manifest {
+    manifest::create_manifest(plan, description.as_ref(), version.as_ref())
+}
+
+fn find_world<'a>(resolve: &'a Resolve, world_name: &str) -> anyhow::Result<&'a World> {
+    resolve
+        .worlds
+        .iter()
+        .find_map(|(_, w)| (w.name == world_name).then_some(w))
+        .with_context(|| format!("component world {world_name} missing"))
+}
+
+fn world_interfaces(resolve: &Resolve, world: &World) -> ComponentInterfaces {
     let wit_parser::World {
         exports, imports, ..
     } = world;
 
-    let manifest = manifest::create_manifest(
-        name.as_ref(),
-        description.as_ref(),
-        version.as_ref(),
-        image.as_ref(),
-        imports
+    ComponentInterfaces {
+        imports: imports
             .iter()
             .map(|(id, _)| resolve.name_world_key(id))
             .collect(),
-        exports
+        exports: exports
             .iter()
             .map(|(id, _)| resolve.name_world_key(id))
             .collect(),
-    );
+    }
+}
 
-    Ok(manifest)
+/// Creates an empty directory for a test to write files to, named after the test
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("wit2wadm-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("should be able to create test directory");
+    dir
+}
+
+#[cfg(test)]
+mod test {
+    use crate::plan::{ApplicationPlan, Direction};
+    use crate::test_util::{component, core_module, resolve};
+    use crate::{plan_wasm, GenerateOptions};
+
+    fn plan(wasm: &[u8], world: Option<&str>) -> anyhow::Result<ApplicationPlan> {
+        plan_wasm(
+            wasm,
+            world,
+            "test",
+            "file://./test.wasm",
+            &GenerateOptions::default(),
+        )
+    }
+
+    /// The imports and exports in a plan
+    fn interfaces(plan: &ApplicationPlan) -> Vec<(Direction, &str)> {
+        plan.interfaces
+            .iter()
+            .map(|decision| (decision.direction, decision.interface.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_wasm() {
+        let server = vec![
+            (Direction::Import, "wit2wadm:test/store"),
+            (Direction::Export, "wit2wadm:test/handler"),
+        ];
+
+        let module = core_module("server");
+        let component = component();
+        assert_eq!(interfaces(&plan(&component, None).unwrap()), server);
+        assert_eq!(interfaces(&plan(&module, None).unwrap()), server);
+        assert_eq!(
+            interfaces(&plan(&core_module("client"), None).unwrap()),
+            vec![(Direction::Import, "wit2wadm:test/handler")]
+        );
+
+        let (resolve, package) = resolve();
+        let wit = wit_component::encode(Some(true), &resolve, package).unwrap();
+        assert_eq!(interfaces(&plan(&wit, Some("server")).unwrap()), server);
+        assert_eq!(
+            interfaces(&plan(&wit, Some("client")).unwrap()),
+            vec![(Direction::Import, "wit2wadm:test/handler")]
+        );
+        // A package with several worlds needs one to be selected
+        assert!(plan(&wit, None).is_err());
+        assert!(plan(&wit, Some("missing")).is_err());
+
+        // A core module that wasn't built for a component has nothing to plan from
+        assert!(plan(b"\0asm\x01\0\0\0", None).is_err());
+        assert!(plan(b"package wit2wadm:test;", None).is_err());
+    }
 }
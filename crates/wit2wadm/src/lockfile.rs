@@ -0,0 +1,164 @@
+//! The `wit2wadm.lock` file pins provider images to digests so regenerating a manifest never
+//! silently picks up a new provider build. Entries are added from OCI image layouts on disk, so
+//! creating and using the lockfile works entirely offline.
+//!
+//! ```toml
+//! [images]
+//! "ghcr.io/wasmcloud/http-server:0.20.0" = "sha256:..."
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use crate::oci::{self, strip_digest};
+
+/// The default name of the lockfile
+pub const LOCKFILE_NAME: &str = "wit2wadm.lock";
+
+const LOCKFILE_HEADER: &str = "# This file is generated by wit2wadm, do not edit it by hand\n";
+
+/// A mapping of image references to the digest they are pinned to
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub images: BTreeMap<String, String>,
+}
+
+impl Lockfile {
+    /// Loads a lockfile from disk
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read lockfile {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("failed to parse lockfile {}", path.display()))
+    }
+
+    /// Parses the contents of a lockfile
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        toml::from_str(contents).context("invalid lockfile")
+    }
+
+    /// Loads a lockfile from disk, returning an empty lockfile if it doesn't exist yet
+    pub fn load_or_default(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        if path.as_ref().exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Writes the lockfile to disk
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let contents = toml::to_string(self).context("failed to serialize lockfile")?;
+        std::fs::write(path, format!("{LOCKFILE_HEADER}{contents}"))
+            .with_context(|| format!("failed to write lockfile {}", path.display()))
+    }
+
+    /// Adds or updates the entry for the artifact in an OCI image layout directory or tarball,
+    /// returning the reference and the digest it is now pinned to
+    pub fn lock_from_layout(&mut self, path: impl AsRef<Path>) -> anyhow::Result<(String, String)> {
+        let artifact = oci::read_reference(path)?;
+        self.images
+            .insert(artifact.reference.clone(), artifact.digest.clone());
+        Ok((artifact.reference, artifact.digest))
+    }
+
+    /// Pins an image to its locked digest, e.g. `ghcr.io/wasmcloud/http-server:0.20.0` becomes
+    /// `ghcr.io/wasmcloud/http-server:0.20.0@sha256:...`. Returns `None` if the image isn't locked
+    pub fn pin(&self, image: &str) -> Option<String> {
+        let reference = strip_digest(image);
+        self.images
+            .get(reference)
+            .map(|digest| format!("{reference}@{digest}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Lockfile, LOCKFILE_HEADER};
+    use crate::oci::strip_digest;
+    use crate::test_dir;
+
+    /// Writes an OCI image layout whose only manifest is annotated with the reference
+    fn write_layout(dir: &std::path::Path, reference: &str, digest: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("oci-layout"), r#"{"imageLayoutVersion":"1.0.0"}"#).unwrap();
+        let index = serde_json::json!({
+            "schemaVersion": 2,
+            "manifests": [{
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": digest,
+                "size": 0,
+                "annotations": { "org.opencontainers.image.ref.name": reference },
+            }],
+        });
+        std::fs::write(dir.join("index.json"), index.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_lockfile() {
+        let dir = test_dir("lockfile");
+        let path = dir.join("wit2wadm.lock");
+
+        let mut lockfile = Lockfile::load_or_default(&path).unwrap();
+        assert_eq!(lockfile, Lockfile::default());
+
+        write_layout(
+            &dir.join("http-server"),
+            "ghcr.io/wasmcloud/http-server:0.20.0",
+            "sha256:1111",
+        );
+        let locked = lockfile.lock_from_layout(dir.join("http-server")).unwrap();
+        assert_eq!(
+            locked,
+            (
+                "ghcr.io/wasmcloud/http-server:0.20.0".to_string(),
+                "sha256:1111".to_string()
+            )
+        );
+
+        // Relocking replaces the digest, and a reference that already has a digest is locked
+        // without it
+        write_layout(
+            &dir.join("rebuilt"),
+            "ghcr.io/wasmcloud/http-server:0.20.0@sha256:0000",
+            "sha256:2222",
+        );
+        lockfile.lock_from_layout(dir.join("rebuilt")).unwrap();
+        assert_eq!(lockfile.images.len(), 1);
+        assert!(lockfile.lock_from_layout(dir.join("missing")).is_err());
+
+        lockfile.save(&path).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with(LOCKFILE_HEADER));
+        assert_eq!(Lockfile::load(&path).unwrap(), lockfile);
+        assert_eq!(Lockfile::load_or_default(&path).unwrap(), lockfile);
+
+        assert_eq!(
+            lockfile.pin("ghcr.io/wasmcloud/http-server:0.20.0"),
+            Some("ghcr.io/wasmcloud/http-server:0.20.0@sha256:2222".to_string())
+        );
+        assert_eq!(
+            lockfile.pin("ghcr.io/wasmcloud/http-server:0.20.0@sha256:1111"),
+            Some("ghcr.io/wasmcloud/http-server:0.20.0@sha256:2222".to_string())
+        );
+        assert_eq!(lockfile.pin("ghcr.io/wasmcloud/http-server:canary"), None);
+        assert_eq!(
+            strip_digest("ghcr.io/wasmcloud/http-server@sha256:1111"),
+            "ghcr.io/wasmcloud/http-server"
+        );
+        assert_eq!(
+            strip_digest("localhost:5000/echo:0.1.0"),
+            "localhost:5000/echo:0.1.0"
+        );
+
+        assert!(Lockfile::parse("images = 1").is_err());
+        std::fs::write(&path, "images = 1").unwrap();
+        assert!(Lockfile::load(&path).is_err());
+    }
+}
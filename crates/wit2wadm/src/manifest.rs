@@ -1,27 +1,19 @@
 use std::collections::BTreeMap;
 
 use wadm::model::{
-    Component, ComponentProperties, Manifest, Metadata, Properties, Specification,
-    SpreadScalerProperty, Trait, TraitProperty, APPLICATION_KIND, LINK_TRAIT, OAM_VERSION,
-    SPREADSCALER_TRAIT,
+    CapabilityProperties, Component, ComponentProperties, ConfigProperty, LinkProperty, Manifest,
+    Metadata, Properties, Specification, SpreadScalerProperty, Trait, TraitProperty,
+    APPLICATION_KIND, LINK_TRAIT, OAM_VERSION, SPREADSCALER_TRAIT,
 };
 
-use crate::interface::{self, combine_interfaces, DirectionalInterface};
-
-/// Create a manifest from metadata about a component and a list of imports and exports
-/// specified in that component's WIT world
-pub fn create_manifest(
-    name: &str,
-    description: &str,
-    version: &str,
-    image: &str,
-    imports: Vec<String>,
-    exports: Vec<String>,
-) -> Manifest {
-    let components = manifest_for_component(name, image, imports, exports);
+use crate::plan::{ApplicationPlan, ConfigPlan, LinkPlan};
+
+/// Create a manifest from an [ApplicationPlan] for a component
+pub fn create_manifest(plan: &ApplicationPlan, description: &str, version: &str) -> Manifest {
+    let components = manifest_for_component(plan);
     Manifest {
         spec: Specification { components },
-        ..base_manifest(name, description, version)
+        ..base_manifest(&plan.name, description, version)
     }
 }
 
@@ -64,89 +56,138 @@ pub fn base_manifest(name: &str, description: &str, version: &str) -> Manifest {
     }
 }
 
-fn manifest_for_component(
-    name: &str,
-    image: &str,
-    imports: Vec<String>,
-    exports: Vec<String>,
-) -> Vec<Component> {
-    let mut component = Component {
-        name: name.to_string(),
-        properties: Properties::Component {
-            properties: ComponentProperties {
-                image: image.to_string(),
-                id: None,
-                config: Vec::new(),
+fn manifest_for_component(plan: &ApplicationPlan) -> Vec<Component> {
+    let components = plan.components.iter().map(|component| {
+        // Ensure the component has a spreadscaler trait
+        let mut traits = vec![Trait {
+            trait_type: SPREADSCALER_TRAIT.to_string(),
+            properties: TraitProperty::SpreadScaler(SpreadScalerProperty {
+                instances: 1,
+                spread: vec![],
+            }),
+        }];
+        traits.extend(link_traits(plan, &component.name));
+
+        Component {
+            name: component.name.clone(),
+            properties: Properties::Component {
+                properties: ComponentProperties {
+                    image: component.image.clone(),
+                    id: None,
+                    config: component.config.iter().map(config_property).collect(),
+                },
             },
-        },
-        traits: None,
-    };
+            traits: Some(traits),
+        }
+    });
 
-    let imports_for_manifest = combine_interfaces(
-        imports
-            .iter()
-            .filter_map(|import| {
-                DirectionalInterface::parse_for_manifest(import, interface::Direction::Import)
-            })
-            .collect::<Vec<_>>(),
-    );
-
-    let link_properties: Vec<Trait> = imports_for_manifest
-        .iter()
-        .filter_map(|import| import.to_target_link_property())
-        .map(|properties| Trait {
-            trait_type: LINK_TRAIT.to_string(),
-            properties: TraitProperty::Link(properties),
-        })
-        .collect();
-
-    let provider_components = imports_for_manifest
-        .iter()
-        .filter_map(|import| import.to_capability_component());
-
-    let exports_for_manifest = combine_interfaces(
-        exports
-            .iter()
-            .filter_map(|export| {
-                DirectionalInterface::parse_for_manifest(export, interface::Direction::Export)
-            })
-            .collect::<Vec<_>>(),
-    );
-
-    let export_components = exports_for_manifest.iter().filter_map(|export| {
-        // When a component has an export, it's a different provider component in the manifest that will link to the component
-        // so we can simply generate the link and then create a new component with that link
-        export.to_source_link_property(name).and_then(|link| {
-            export.to_capability_component().map(|component| Component {
-                traits: Some(vec![Trait {
-                    trait_type: LINK_TRAIT.to_string(),
-                    properties: TraitProperty::Link(link),
-                }]),
-                ..component
-            })
-        })
+    // When a component has an export, it's a provider component in the manifest that links to
+    // the component, so providers carry their own link traits
+    let provider_components = plan.providers.iter().map(|provider| {
+        let traits: Vec<Trait> = link_traits(plan, &provider.name).collect();
+        Component {
+            name: provider.name.clone(),
+            properties: Properties::Capability {
+                properties: CapabilityProperties {
+                    image: provider.image.clone(),
+                    id: None,
+                    config: provider.config.iter().map(config_property).collect(),
+                },
+            },
+            traits: (!traits.is_empty()).then_some(traits),
+        }
     });
 
-    // Ensure the component has a spreadscaler trait
-    let mut traits = vec![Trait {
-        trait_type: SPREADSCALER_TRAIT.to_string(),
-        properties: TraitProperty::SpreadScaler(SpreadScalerProperty {
-            instances: 1,
-            spread: vec![],
-        }),
-    }];
-    traits.extend(link_properties);
-    component.traits = Some(traits);
-
-    let mut out_vec = Vec::new();
-    out_vec.push(component);
-    out_vec.extend(provider_components.chain(export_components));
+    let mut out_vec: Vec<Component> = components.collect();
+    out_vec.extend(provider_components);
 
     out_vec
 }
 
+fn link_traits<'a>(plan: &'a ApplicationPlan, source: &'a str) -> impl Iterator<Item = Trait> + 'a {
+    plan.links_from(source).map(|link| Trait {
+        trait_type: LINK_TRAIT.to_string(),
+        properties: TraitProperty::Link(link_property(link)),
+    })
+}
+
+fn link_property(link: &LinkPlan) -> LinkProperty {
+    LinkProperty {
+        target: link.target.clone(),
+        namespace: link.namespace.clone(),
+        package: link.package.clone(),
+        interfaces: link.interfaces.clone(),
+        source_config: link.source_config.iter().map(config_property).collect(),
+        target_config: link.target_config.iter().map(config_property).collect(),
+        name: link.name.clone(),
+    }
+}
+
+fn config_property(config: &ConfigPlan) -> ConfigProperty {
+    ConfigProperty {
+        name: config.name.clone(),
+        properties: Some(config.properties.clone().into_iter().collect()),
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use wadm::model::{Properties, TraitProperty};
+
+    use super::manifest_for_component;
+    use crate::plan::plan_application;
+    use crate::GenerateOptions;
+
     #[test]
-    fn test_manifest_for_component() {}
+    fn test_manifest_for_component() {
+        let plan = plan_application(
+            "echo",
+            "file://./echo.wasm",
+            &[
+                "wasi:io/streams@0.2.0".to_string(),
+                "wasi:http/types@0.2.0".to_string(),
+                "wasi:keyvalue/store@0.2.0-draft".to_string(),
+                "wasi:keyvalue/atomics@0.2.0-draft".to_string(),
+            ],
+            &["wasi:http/incoming-handler@0.2.0".to_string()],
+            &GenerateOptions::default(),
+        );
+        let components = manifest_for_component(&plan);
+
+        let names: Vec<&str> = components.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["echo", "wasi:keyvalue-target", "wasi:http-source"]
+        );
+
+        // The component links to the keyvalue provider
+        let links: Vec<_> = components[0]
+            .traits
+            .iter()
+            .flatten()
+            .filter_map(|t| match &t.properties {
+                TraitProperty::Link(link) => Some(link),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].target, "wasi:keyvalue-target");
+        assert_eq!(links[0].interfaces, vec!["store", "atomics"]);
+
+        // The keyvalue provider has no links of its own
+        assert!(components[1].traits.is_none());
+        assert!(matches!(
+            &components[1].properties,
+            Properties::Capability { properties } if properties.image.contains("keyvalue-redis")
+        ));
+
+        // The http server links to the component
+        match &components[2].traits.as_ref().unwrap()[0].properties {
+            TraitProperty::Link(link) => {
+                assert_eq!(link.target, "echo");
+                assert_eq!(link.interfaces, vec!["incoming-handler"]);
+            }
+            _ => panic!("http server should have a link trait"),
+        }
+    }
 }
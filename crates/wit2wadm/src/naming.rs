@@ -0,0 +1,155 @@
+//! Names for the providers and links wit2wadm generates. Names come from templates so they can
+//! match the conventions of the tooling a manifest is deployed with, and names that would collide
+//! in the manifest are disambiguated deterministically.
+
+use std::collections::HashMap;
+
+use crate::interface::Direction;
+use crate::plan::LinkPlan;
+use crate::GenerateOptions;
+
+/// The template used to name providers when none is given, e.g. `wasi:keyvalue-target`
+pub const DEFAULT_PROVIDER_NAME: &str = "{namespace}:{package}-{direction}";
+
+/// Renders a name template. The placeholders are `{app}`, `{namespace}`, `{package}`,
+/// `{version}` and `{direction}`, which is `target` for imports and `source` for exports.
+/// Placeholders without a value, like the version of an unversioned package, render as nothing
+/// and leave no stray separators behind
+pub fn render(
+    template: &str,
+    app: &str,
+    namespace: &str,
+    package: &str,
+    version: Option<&str>,
+    direction: Option<Direction>,
+) -> String {
+    let rendered = template
+        .replace("{app}", app)
+        .replace("{namespace}", namespace)
+        .replace("{package}", package)
+        .replace("{version}", version.unwrap_or_default())
+        .replace(
+            "{direction}",
+            direction.map(|d| d.as_str()).unwrap_or_default(),
+        );
+
+    let mut name = String::with_capacity(rendered.len());
+    for c in rendered.chars() {
+        if c == '-' && (name.is_empty() || name.ends_with('-')) {
+            continue;
+        }
+        name.push(c);
+    }
+    name.trim_end_matches('-').to_string()
+}
+
+/// Renders the name of the provider for a package from the template in the options
+pub fn provider_name(
+    options: &GenerateOptions,
+    app: &str,
+    namespace: &str,
+    package: &str,
+    version: Option<&str>,
+    direction: Option<Direction>,
+) -> String {
+    let template = options
+        .provider_name
+        .as_deref()
+        .unwrap_or(DEFAULT_PROVIDER_NAME);
+    render(template, app, namespace, package, version, direction)
+}
+
+/// Returns the name unchanged if it isn't taken, and otherwise appends the version (when there
+/// is one) or the first free number starting from 2
+pub fn unique_name(name: String, version: Option<&str>, taken: &[String]) -> String {
+    if !taken.contains(&name) {
+        return name;
+    }
+    if let Some(version) = version {
+        let versioned = format!("{name}-{version}");
+        if !taken.contains(&versioned) {
+            return versioned;
+        }
+    }
+    (2..)
+        .map(|n| format!("{name}-{n}"))
+        .find(|candidate| !taken.contains(candidate))
+        .expect("there is always a free name")
+}
+
+/// Names the copy of a provider dedicated to one component of a composed application, e.g. its
+/// own HTTP server, after the component and the provider it replaces. Like [unique_name], a name
+/// that's already taken gets the first free number starting from 2
+pub fn component_provider_name(component: &str, provider: &str, taken: &[String]) -> String {
+    unique_name(format!("{component}-{provider}"), None, taken)
+}
+
+/// Names links that wadm couldn't tell apart: links from the same source for the same package
+/// with the same name, which includes several links left with the default name. Each of them is
+/// given the name of its target, appended to its name if it has one
+pub fn disambiguate_links(links: &mut [LinkPlan]) {
+    let key = |link: &LinkPlan| {
+        (
+            link.source.clone(),
+            link.namespace.clone(),
+            link.package.clone(),
+            link.name.clone(),
+        )
+    };
+    let mut counts: HashMap<(String, String, String, Option<String>), usize> = HashMap::new();
+    for link in links.iter() {
+        *counts.entry(key(link)).or_default() += 1;
+    }
+    for link in links.iter_mut() {
+        if counts[&key(link)] > 1 {
+            link.name = Some(match &link.name {
+                Some(name) => format!("{name}-{}", link.target),
+                None => link.target.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, unique_name, DEFAULT_PROVIDER_NAME};
+    use crate::interface::Direction;
+
+    #[test]
+    fn test_naming() {
+        let keyvalue = |template| {
+            render(
+                template,
+                "shop",
+                "wasi",
+                "keyvalue",
+                Some("0.2.0-draft"),
+                Some(Direction::Import),
+            )
+        };
+        assert_eq!(keyvalue(DEFAULT_PROVIDER_NAME), "wasi:keyvalue-target");
+        assert_eq!(keyvalue("{package}-{direction}"), "keyvalue-target");
+        assert_eq!(keyvalue("{app}-{package}"), "shop-keyvalue");
+
+        let consolidated = render(
+            DEFAULT_PROVIDER_NAME,
+            "shop",
+            "wasi",
+            "keyvalue",
+            None,
+            None,
+        );
+        assert_eq!(consolidated, "wasi:keyvalue");
+
+        let taken = vec!["keyvalue".to_string(), "keyvalue-0.2.0".to_string()];
+        assert_eq!(unique_name("http".to_string(), None, &taken), "http");
+        assert_eq!(
+            unique_name("keyvalue".to_string(), Some("0.1.0"), &taken),
+            "keyvalue-0.1.0"
+        );
+        assert_eq!(
+            unique_name("keyvalue".to_string(), Some("0.2.0"), &taken),
+            "keyvalue-2"
+        );
+    }
+}
@@ -0,0 +1,503 @@
+//! Support for reading components out of OCI artifacts stored on disk, either as an
+//! [OCI image layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md)
+//! directory or as a tarball of one. Nothing here touches the network.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read as _;
+use std::path::Path;
+
+use anyhow::{bail, Context as _};
+use serde::Deserialize;
+
+/// The annotation used on an index descriptor to record the reference of the artifact
+pub const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+
+const OCI_LAYOUT_FILE: &str = "oci-layout";
+const INDEX_FILE: &str = "index.json";
+
+/// A component loaded from disk, along with the image reference it was stored under if known
+pub struct ComponentArtifact {
+    /// The raw bytes of the WebAssembly component
+    pub wasm: Vec<u8>,
+    /// The digest-pinned image reference of the artifact, e.g.
+    /// `ghcr.io/wasmcloud/components/http-hello-world-rust:0.1.0@sha256:...`. This is only
+    /// set when the component was read from an OCI artifact annotated with a full reference
+    pub image: Option<String>,
+}
+
+/// Subset of an OCI image index or image manifest, enough to locate the wasm layer
+#[derive(Deserialize)]
+struct OciManifest {
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    #[serde(default)]
+    media_type: String,
+    digest: String,
+    #[serde(default)]
+    annotations: BTreeMap<String, String>,
+}
+
+impl Descriptor {
+    fn is_index(&self) -> bool {
+        self.media_type == "application/vnd.oci.image.index.v1+json"
+    }
+
+    fn is_wasm(&self) -> bool {
+        // wasmCloud uses `application/vnd.module.wasm.content.layer.v1+wasm` while the CNCF
+        // wasm artifact guidance uses `application/wasm`
+        self.media_type.ends_with("wasm")
+    }
+
+    fn blob_path(&self) -> anyhow::Result<String> {
+        let (algorithm, hex) = self
+            .digest
+            .split_once(':')
+            .with_context(|| format!("invalid digest {}", self.digest))?;
+        Ok(format!("blobs/{algorithm}/{hex}"))
+    }
+}
+
+/// The reference and manifest digest of the artifact stored in an OCI image layout
+pub struct ArtifactReference {
+    /// The reference from the `org.opencontainers.image.ref.name` annotation
+    pub reference: String,
+    /// The digest of the artifact's manifest, e.g. `sha256:...`
+    pub digest: String,
+}
+
+/// An OCI image layout, either in a directory read with the given function or unpacked from a
+/// tarball into memory
+enum Layout<'a> {
+    Directory {
+        root: &'a Path,
+        read: &'a dyn Fn(&Path) -> anyhow::Result<Vec<u8>>,
+    },
+    Tarball {
+        files: HashMap<String, Vec<u8>>,
+        prefix: String,
+    },
+}
+
+impl Layout<'_> {
+    /// Reads a file relative to the root of the layout
+    fn read(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Layout::Directory { root, read } => read(&root.join(name))
+                .with_context(|| format!("failed to read {name} from OCI layout")),
+            Layout::Tarball { files, prefix } => files
+                .get(&format!("{prefix}{name}"))
+                .cloned()
+                .with_context(|| format!("tarball is missing {name}")),
+        }
+    }
+
+    /// Returns the descriptor of the single manifest referenced by the layout's `index.json`
+    fn manifest_descriptor(&self) -> anyhow::Result<Descriptor> {
+        let index: OciManifest = serde_json::from_slice(&self.read(INDEX_FILE)?)
+            .context("failed to parse OCI index.json")?;
+
+        let mut manifests = index.manifests;
+        match manifests.len() {
+            1 => Ok(manifests.remove(0)),
+            0 => bail!("OCI index.json does not reference any manifests"),
+            n => bail!(
+                "OCI layout contains {n} manifests, expected exactly one: {}",
+                manifests
+                    .iter()
+                    .map(|d| d
+                        .annotations
+                        .get(REF_NAME_ANNOTATION)
+                        .unwrap_or(&d.digest)
+                        .as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Returns true if the given path is a directory containing an OCI image layout
+pub fn is_oci_layout(path: impl AsRef<Path>) -> bool {
+    is_oci_layout_in(path.as_ref(), Path::is_file)
+}
+
+/// Returns true if the files of an OCI image layout are in the given directory, checking for
+/// them with `exists`. This is [is_oci_layout] for filesystems other than the local one
+pub fn is_oci_layout_in(path: &Path, exists: impl Fn(&Path) -> bool) -> bool {
+    exists(&path.join(OCI_LAYOUT_FILE)) && exists(&path.join(INDEX_FILE))
+}
+
+/// Reads a component from a path that may be a raw `.wasm` file, an OCI image layout directory
+/// or a (optionally gzipped) tarball of an OCI image layout
+pub fn read_component(path: impl AsRef<Path>) -> anyhow::Result<ComponentArtifact> {
+    let path = path.as_ref();
+    if is_oci_layout(path) {
+        return read_layout(path, read_file);
+    }
+
+    let bytes = std::fs::read(path).context("failed to read WIT component")?;
+    read_component_bytes(bytes)
+}
+
+/// Reads a component from the OCI image layout directory at `root`, reading the layout's files
+/// with `read`. This is [read_component] for filesystems other than the local one
+pub fn read_layout(
+    root: &Path,
+    read: impl Fn(&Path) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<ComponentArtifact> {
+    read_layout_component(&Layout::Directory { root, read: &read })
+}
+
+/// Reads a component from bytes that may be a raw `.wasm` file or a (optionally gzipped)
+/// tarball of an OCI image layout. Raw wasm is returned as-is with no image reference
+pub fn read_component_bytes(bytes: Vec<u8>) -> anyhow::Result<ComponentArtifact> {
+    match unpack_tarball(bytes)? {
+        Ok(layout) => read_layout_component(&layout),
+        Err(wasm) => Ok(ComponentArtifact { wasm, image: None }),
+    }
+}
+
+/// Reads the annotated reference and manifest digest of the artifact in an OCI image layout
+/// directory or tarball, without reading any of its layers
+pub fn read_reference(path: impl AsRef<Path>) -> anyhow::Result<ArtifactReference> {
+    let path = path.as_ref();
+    let layout = if is_oci_layout(path) {
+        Layout::Directory {
+            root: path,
+            read: &read_file,
+        }
+    } else {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        unpack_tarball(bytes)?.map_err(|_| {
+            anyhow::anyhow!("{} is not an OCI image layout or tarball", path.display())
+        })?
+    };
+
+    let descriptor = layout.manifest_descriptor()?;
+    let reference = descriptor
+        .annotations
+        .get(REF_NAME_ANNOTATION)
+        .filter(|reference| reference.contains('/'))
+        .with_context(|| {
+            format!(
+                "{} must be annotated with a full reference in {REF_NAME_ANNOTATION}",
+                path.display()
+            )
+        })?;
+    Ok(ArtifactReference {
+        reference: strip_digest(reference).to_string(),
+        digest: descriptor.digest,
+    })
+}
+
+/// Unpacks a (optionally gzipped) tarball of an OCI image layout into memory, handing the bytes
+/// back if they aren't a tarball
+fn unpack_tarball(bytes: Vec<u8>) -> anyhow::Result<Result<Layout<'static>, Vec<u8>>> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decompressed)
+            .context("failed to decompress gzipped tarball")?;
+        return unpack_tarball(decompressed);
+    }
+
+    if !is_tar(&bytes) {
+        return Ok(Err(bytes));
+    }
+
+    let mut files = HashMap::new();
+    let mut archive = tar::Archive::new(bytes.as_slice());
+    for entry in archive.entries().context("failed to read tarball")? {
+        let mut entry = entry.context("failed to read tarball entry")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry
+            .path()
+            .context("invalid path in tarball")?
+            .to_string_lossy()
+            .trim_start_matches("./")
+            .to_string();
+        let mut contents = Vec::new();
+        entry
+            .read_to_end(&mut contents)
+            .with_context(|| format!("failed to read {path} from tarball"))?;
+        files.insert(path, contents);
+    }
+
+    // Tarballs are allowed to nest the layout under a single top level directory
+    let prefix = files
+        .keys()
+        .filter_map(|path| path.strip_suffix(INDEX_FILE))
+        .min_by_key(|prefix| prefix.len())
+        .map(ToString::to_string)
+        .context("tarball does not contain an OCI image layout (missing index.json)")?;
+
+    Ok(Ok(Layout::Tarball { files, prefix }))
+}
+
+fn read_file(path: &Path) -> anyhow::Result<Vec<u8>> {
+    Ok(std::fs::read(path)?)
+}
+
+fn is_tar(bytes: &[u8]) -> bool {
+    bytes.get(257..262) == Some(b"ustar".as_slice())
+}
+
+/// Walks an OCI image layout from its `index.json` to the wasm layer
+fn read_layout_component(layout: &Layout) -> anyhow::Result<ComponentArtifact> {
+    let descriptor = layout.manifest_descriptor()?;
+    let wasm = read_wasm_layer(layout, &descriptor)?;
+    let image = descriptor
+        .annotations
+        .get(REF_NAME_ANNOTATION)
+        .and_then(|reference| pinned_reference(reference, &descriptor.digest));
+
+    Ok(ComponentArtifact { wasm, image })
+}
+
+fn read_wasm_layer(layout: &Layout, descriptor: &Descriptor) -> anyhow::Result<Vec<u8>> {
+    let manifest: OciManifest = serde_json::from_slice(&layout.read(&descriptor.blob_path()?)?)
+        .with_context(|| format!("failed to parse OCI manifest {}", descriptor.digest))?;
+
+    if descriptor.is_index() || !manifest.manifests.is_empty() {
+        let [nested] = manifest.manifests.as_slice() else {
+            bail!(
+                "OCI index {} must reference exactly one manifest",
+                descriptor.digest
+            );
+        };
+        return read_wasm_layer(layout, nested);
+    }
+
+    let wasm_layers: Vec<&Descriptor> = manifest
+        .layers
+        .iter()
+        .filter(|layer| layer.is_wasm())
+        .collect();
+    match wasm_layers.as_slice() {
+        [layer] => layout.read(&layer.blob_path()?),
+        [] => bail!("OCI manifest {} has no wasm layer", descriptor.digest),
+        layers => bail!(
+            "OCI manifest {} has {} wasm layers, expected exactly one",
+            descriptor.digest,
+            layers.len()
+        ),
+    }
+}
+
+/// Pins a reference from the `org.opencontainers.image.ref.name` annotation to the given
+/// manifest digest. Annotations that are only a tag (e.g. `v0.1.0`) can't be turned into a
+/// pullable reference so they return `None`
+fn pinned_reference(reference: &str, digest: &str) -> Option<String> {
+    if !reference.contains('/') {
+        return None;
+    }
+    Some(format!("{}@{digest}", strip_digest(reference)))
+}
+
+/// Removes a `@sha256:...` digest from a reference, if it has one
+pub fn strip_digest(reference: &str) -> &str {
+    reference
+        .split_once('@')
+        .map(|(name, _)| name)
+        .unwrap_or(reference)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io::Write as _;
+    use std::path::{Path, PathBuf};
+
+    use anyhow::Context as _;
+
+    use super::{
+        read_component, read_component_bytes, read_layout, read_reference, REF_NAME_ANNOTATION,
+    };
+    use crate::test_dir;
+
+    const WASM: &[u8] = b"\0asm\x0d\0\x01\0";
+    const MANIFEST_DIGEST: &str = "sha256:1111";
+    const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+    const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+    const WASM_MEDIA_TYPE: &str = "application/vnd.module.wasm.content.layer.v1+wasm";
+
+    /// The files of an OCI image layout whose index references a manifest with the given
+    /// layers, each a media type and its contents, optionally through a nested index
+    fn layout_files(
+        reference: &str,
+        layers: &[(&str, &[u8])],
+        nested: bool,
+    ) -> Vec<(String, Vec<u8>)> {
+        let mut files = vec![(
+            "oci-layout".to_string(),
+            br#"{"imageLayoutVersion": "1.0.0"}"#.to_vec(),
+        )];
+        let layer_descriptors: Vec<serde_json::Value> = layers
+            .iter()
+            .enumerate()
+            .map(|(i, (media_type, contents))| {
+                files.push((format!("blobs/sha256/layer{i}"), contents.to_vec()));
+                serde_json::json!({ "mediaType": media_type, "digest": format!("sha256:layer{i}") })
+            })
+            .collect();
+        let manifest = serde_json::json!({ "layers": layer_descriptors });
+        files.push((
+            "blobs/sha256/1111".to_string(),
+            serde_json::to_vec(&manifest).unwrap(),
+        ));
+
+        let top = if nested {
+            let index = serde_json::json!({
+                "manifests": [{ "mediaType": MANIFEST_MEDIA_TYPE, "digest": MANIFEST_DIGEST }]
+            });
+            files.push((
+                "blobs/sha256/2222".to_string(),
+                serde_json::to_vec(&index).unwrap(),
+            ));
+            ("sha256:2222", INDEX_MEDIA_TYPE)
+        } else {
+            (MANIFEST_DIGEST, MANIFEST_MEDIA_TYPE)
+        };
+        let index = serde_json::json!({
+            "manifests": [{
+                "mediaType": top.1,
+                "digest": top.0,
+                "annotations": { REF_NAME_ANNOTATION: reference },
+            }]
+        });
+        files.push((
+            "index.json".to_string(),
+            serde_json::to_vec(&index).unwrap(),
+        ));
+        files
+    }
+
+    fn write_layout(dir: &Path, files: &[(String, Vec<u8>)]) {
+        for (name, contents) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    /// A gzipped tarball of the layout with every file under `prefix`
+    fn tarball(files: &[(String, Vec<u8>)], prefix: &str) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("{prefix}{name}"), contents.as_slice())
+                .unwrap();
+        }
+        let tar = builder.into_inner().unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_read_layout_component() {
+        let dir = test_dir("oci-layout");
+        let files = layout_files(
+            "ghcr.io/acme/echo:0.1.0",
+            &[
+                ("application/vnd.wasm.config.v0+json", b"{}".as_slice()),
+                (WASM_MEDIA_TYPE, WASM),
+            ],
+            false,
+        );
+        write_layout(&dir, &files);
+
+        let artifact = read_component(&dir).unwrap();
+        assert_eq!(artifact.wasm, WASM);
+        assert_eq!(
+            artifact.image.as_deref(),
+            Some("ghcr.io/acme/echo:0.1.0@sha256:1111")
+        );
+
+        // The same layout read through another filesystem
+        let in_memory: HashMap<PathBuf, Vec<u8>> = files
+            .iter()
+            .map(|(name, contents)| (Path::new("echo").join(name), contents.clone()))
+            .collect();
+        let artifact = read_layout(Path::new("echo"), |path| {
+            in_memory.get(path).cloned().context("missing file")
+        })
+        .unwrap();
+        assert_eq!(artifact.wasm, WASM);
+        assert_eq!(
+            artifact.image.as_deref(),
+            Some("ghcr.io/acme/echo:0.1.0@sha256:1111")
+        );
+        let reference = read_reference(&dir).unwrap();
+        assert_eq!(reference.reference, "ghcr.io/acme/echo:0.1.0");
+        assert_eq!(reference.digest, MANIFEST_DIGEST);
+
+        // The same layout in a gzipped tarball, nested under a top level directory
+        let artifact = read_component_bytes(tarball(&files, "./echo/")).unwrap();
+        assert_eq!(artifact.wasm, WASM);
+        assert_eq!(
+            artifact.image.as_deref(),
+            Some("ghcr.io/acme/echo:0.1.0@sha256:1111")
+        );
+
+        // Raw wasm is passed through without an image
+        let artifact = read_component_bytes(WASM.to_vec()).unwrap();
+        assert_eq!(artifact.wasm, WASM);
+        assert!(artifact.image.is_none());
+    }
+
+    #[test]
+    fn test_read_unusual_layouts() {
+        // An index nested in the index is followed to the manifest
+        let files = layout_files("ghcr.io/acme/echo:0.1.0", &[(WASM_MEDIA_TYPE, WASM)], true);
+        let artifact = read_component_bytes(tarball(&files, "")).unwrap();
+        assert_eq!(artifact.wasm, WASM);
+        assert_eq!(
+            artifact.image.as_deref(),
+            Some("ghcr.io/acme/echo:0.1.0@sha256:2222")
+        );
+
+        // A reference that's only a tag can't be pulled, so there's no image
+        let files = layout_files("0.1.0", &[(WASM_MEDIA_TYPE, WASM)], false);
+        let artifact = read_component_bytes(tarball(&files, "")).unwrap();
+        assert_eq!(artifact.wasm, WASM);
+        assert!(artifact.image.is_none());
+        assert!(read_reference_bytes(&files).is_err());
+
+        // The manifest has to have exactly one wasm layer
+        let files = layout_files("ghcr.io/acme/echo:0.1.0", &[], false);
+        assert!(read_component_bytes(tarball(&files, "")).is_err());
+        let files = layout_files(
+            "ghcr.io/acme/echo:0.1.0",
+            &[(WASM_MEDIA_TYPE, WASM), ("application/wasm", WASM)],
+            false,
+        );
+        assert!(read_component_bytes(tarball(&files, "")).is_err());
+
+        // A tarball without a layout isn't a component
+        let mut files = layout_files("ghcr.io/acme/echo:0.1.0", &[(WASM_MEDIA_TYPE, WASM)], false);
+        files.retain(|(name, _)| name != "index.json");
+        assert!(read_component_bytes(tarball(&files, "")).is_err());
+    }
+
+    fn read_reference_bytes(files: &[(String, Vec<u8>)]) -> anyhow::Result<()> {
+        let dir = test_dir("oci-reference");
+        let path = dir.join("artifact.tar.gz");
+        std::fs::write(&path, tarball(files, "")).unwrap();
+        read_reference(&path).map(|_| ())
+    }
+}
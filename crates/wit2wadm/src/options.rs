@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use crate::catalog::ImageChannel;
+use crate::compose::HttpRouting;
+use crate::lockfile::Lockfile;
+
+/// Options that control how a manifest is generated
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct GenerateOptions {
+    /// Which tag of well known provider images to use
+    pub provider_images: ImageChannel,
+    /// The backend to use for a package with several known providers, keyed by the package
+    /// with or without its namespace, e.g. `keyvalue = "nats"` or `"wasi:blobstore" = "s3"`
+    pub backends: BTreeMap<String, String>,
+    /// Provider images to link interfaces to instead of the built-in rules, keyed by
+    /// `namespace:package/interface` or `namespace:package`. This also links interfaces that are
+    /// normally implemented by the host, e.g. `wasi:logging` to a custom logging provider
+    pub provider_overrides: BTreeMap<String, String>,
+    /// The template for provider names, see [naming](crate::naming). Defaults to
+    /// [DEFAULT_PROVIDER_NAME](crate::naming::DEFAULT_PROVIDER_NAME)
+    pub provider_name: Option<String>,
+    /// The template for link names, see [naming](crate::naming). Links use wadm's default name
+    /// when this isn't set, unless that would collide
+    pub link_name: Option<String>,
+    /// Packages that keep a separate provider for their imports and exports, with or without
+    /// their namespace. Other packages share one provider for both when the image is the same
+    pub separate_providers: Vec<String>,
+    /// Interfaces to leave out of the manifest, as `namespace:package/interface` or
+    /// `namespace:package`, e.g. ones satisfied by a component composed into this one
+    pub ignore: Vec<String>,
+    /// Packages to treat as another package, e.g. `"acme:kv" = "wasi:keyvalue"` for a renamed
+    /// copy of a standard interface. The target may include a version
+    pub aliases: BTreeMap<String, String>,
+    /// Config for the provider's side of links, keyed by package like `backends`. These are
+    /// merged over the defaults for well known providers
+    pub provider_config: BTreeMap<String, BTreeMap<String, String>>,
+    /// How requests are routed when several components export `wasi:http`
+    pub http_routing: HttpRouting,
+    /// The HTTP route of each component, keyed by component name. This is a path or host when
+    /// routing by path or host, otherwise the address (or just the port) of its HTTP server
+    pub http_routes: BTreeMap<String, String>,
+    /// Subjects that components exporting `wasmcloud:messaging/handler` subscribe to. These
+    /// replace the default subscription for the application
+    pub subscriptions: Vec<Subscription>,
+    /// Config for components that import `wasi:config`, added to them as named config
+    pub config: BTreeMap<String, String>,
+    /// Digests to pin provider images to, usually loaded from `wit2wadm.lock`
+    #[serde(skip)]
+    pub lockfile: Option<Lockfile>,
+}
+
+/// A subject a messaging handler subscribes to, optionally as part of a queue group
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Subscription {
+    pub subject: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<String>,
+}
+
+impl Subscription {
+    /// The subscription as written in the messaging provider's `subscriptions` link config, i.e.
+    /// `subject` or `subject|queue`
+    pub fn link_value(&self) -> String {
+        match &self.queue {
+            Some(queue) => format!("{}|{queue}", self.subject),
+            None => self.subject.clone(),
+        }
+    }
+}
+
+impl GenerateOptions {
+    /// Adds provider config from the contents of a TOML file with a table per package, e.g.
+    /// `[http]` with `address = "0.0.0.0:8080"`. Values already set in the options win, so
+    /// flags can override the file
+    pub fn add_provider_config_file(&mut self, contents: &str) -> anyhow::Result<()> {
+        let file: BTreeMap<String, BTreeMap<String, String>> =
+            toml::from_str(contents).context("invalid provider config file")?;
+        for (package, properties) in file {
+            let existing = self.provider_config.entry(package).or_default();
+            for (key, value) in properties {
+                existing.entry(key).or_insert(value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds component config from the contents of a TOML file of string values. Values already
+    /// set in the options win, so flags can override the file
+    pub fn add_config_file(&mut self, contents: &str) -> anyhow::Result<()> {
+        let file: BTreeMap<String, String> =
+            toml::from_str(contents).context("invalid config file")?;
+        for (key, value) in file {
+            self.config.entry(key).or_insert(value);
+        }
+        Ok(())
+    }
+}
+
+/// Looks up an option keyed by package, preferring `namespace:package` over just `package`
+pub(crate) fn package_option<'a, T>(
+    options: &'a BTreeMap<String, T>,
+    namespace: &str,
+    package: &str,
+) -> Option<&'a T> {
+    options
+        .get(&format!("{namespace}:{package}"))
+        .or_else(|| options.get(package))
+}
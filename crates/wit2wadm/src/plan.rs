@@ -0,0 +1,1273 @@
+//! An application plan is the intermediate step between analyzing a component's WIT world and
+//! rendering a wadm manifest. It records every import and export of the world, how each one was
+//! classified and why, and the providers and links that the manifest will contain.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::catalog::{backends, find_provider, CatalogProvider};
+pub use crate::interface::Direction;
+use crate::interface::{combine_interfaces, CombinedInterface, DirectionalInterface};
+use crate::naming;
+use crate::options::package_option;
+use crate::GenerateOptions;
+
+/// The messaging provider's link config property listing the subjects to subscribe to
+const SUBSCRIPTIONS_PROPERTY: &str = "subscriptions";
+
+/// A plan for turning one or more components into a wadm application
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplicationPlan {
+    /// The name of the application. A single component application uses it for the component too
+    pub name: String,
+    /// The components of the application
+    pub components: Vec<ComponentPlan>,
+    /// Every import and export of the components' worlds and the decision made for it
+    pub interfaces: Vec<InterfaceDecision>,
+    /// Capability providers that will be added to the application
+    pub providers: Vec<ProviderPlan>,
+    /// Links that will be added to the application
+    pub links: Vec<LinkPlan>,
+    /// Problems with the plan that won't stop the manifest from deploying but probably stop the
+    /// application from working
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// A WebAssembly component in the manifest
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentPlan {
+    pub name: String,
+    pub image: String,
+    /// Named config for the component, read through `wasi:config`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config: Vec<ConfigPlan>,
+}
+
+/// The decision made for a single import or export
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceDecision {
+    /// The name of the component with this import or export
+    pub component: String,
+    /// The name of the import or export, e.g. `wasi:http/incoming-handler@0.2.0`
+    pub interface: String,
+    pub direction: Direction,
+    pub classification: Classification,
+    /// A human readable explanation of why this classification was chosen
+    pub reason: String,
+}
+
+/// How an import or export is represented in the manifest
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Classification {
+    /// Implemented by the wasmCloud host, nothing is added to the manifest
+    HostProvided,
+    /// Backed by a well known capability provider
+    Provider { provider: String, image: String },
+    /// A custom interface, backed by a provider with a placeholder image
+    Custom { provider: String, image: String },
+    /// Only defines types used by other interfaces, nothing is added to the manifest
+    TypeOnly,
+    /// Not an interface that can be linked, e.g. a function defined in the world itself
+    Ignored,
+}
+
+/// A capability provider component in the manifest
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderPlan {
+    pub name: String,
+    pub image: String,
+    pub namespace: String,
+    pub package: String,
+    pub version: Option<String>,
+    /// The direction of the interfaces the provider serves. A provider consolidated to serve
+    /// both the imports and exports of a package keeps the direction of the imports
+    pub direction: Direction,
+    pub interfaces: Vec<String>,
+    /// Named config for the provider itself, rather than for one of its links
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config: Vec<ConfigPlan>,
+}
+
+/// A link trait in the manifest, attached to the `source` component
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkPlan {
+    pub source: String,
+    pub target: String,
+    pub namespace: String,
+    pub package: String,
+    pub interfaces: Vec<String>,
+    /// The name of the link, only needed when the source has several links for the same package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_config: Vec<ConfigPlan>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_config: Vec<ConfigPlan>,
+}
+
+/// Named config with its properties, attached to a component or a link
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigPlan {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl Classification {
+    /// A short label for the classification, matching its serialized `kind`
+    pub fn label(&self) -> &'static str {
+        match self {
+            Classification::HostProvided => "host-provided",
+            Classification::Provider { .. } => "provider",
+            Classification::Custom { .. } => "custom",
+            Classification::TypeOnly => "type-only",
+            Classification::Ignored => "ignored",
+        }
+    }
+}
+
+impl ApplicationPlan {
+    /// Returns the links whose source is the component or provider with the given name
+    pub fn links_from<'a>(&'a self, source: &'a str) -> impl Iterator<Item = &'a LinkPlan> + 'a {
+        self.links.iter().filter(move |link| link.source == source)
+    }
+}
+
+/// Plans an application for a component with the given name and image from the names of the
+/// imports and exports of its WIT world
+pub fn plan_application(
+    name: &str,
+    image: &str,
+    imports: &[String],
+    exports: &[String],
+    options: &GenerateOptions,
+) -> ApplicationPlan {
+    let mut plan = ApplicationPlan {
+        name: name.to_string(),
+        components: vec![ComponentPlan {
+            name: name.to_string(),
+            image: image.to_string(),
+            config: Vec::new(),
+        }],
+        interfaces: Vec::new(),
+        providers: Vec::new(),
+        links: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let names: Vec<(&String, Direction)> = imports
+        .iter()
+        .map(|i| (i, Direction::Import))
+        .chain(exports.iter().map(|e| (e, Direction::Export)))
+        .collect();
+
+    // Ignore rules are applied first. Alias rules only choose the provider of an interface, so
+    // they're applied when classifying it and its link keeps its own package
+    let parsed: Vec<Option<DirectionalInterface>> = names
+        .iter()
+        .map(|(interface, direction)| {
+            DirectionalInterface::parse(interface, *direction)
+                .filter(|parsed| !is_ignored(parsed, options))
+        })
+        .collect();
+
+    let linkable = parsed
+        .iter()
+        .flatten()
+        .filter(|parsed| host_provided(parsed, options).is_none() && !parsed.is_type_only())
+        .cloned()
+        .collect::<Vec<_>>();
+    let combined_interfaces = combine_interfaces(linkable);
+
+    let imports_config = imports.iter().any(|import| {
+        DirectionalInterface::parse(import, Direction::Import)
+            .is_some_and(|parsed| parsed.namespace == "wasi" && parsed.package == "config")
+    });
+    match (imports_config, options.config.is_empty()) {
+        (true, false) => plan.components[0].config.push(ConfigPlan {
+            name: format!("{name}-config"),
+            properties: options.config.clone(),
+        }),
+        (false, false) => plan.warnings.push(format!(
+            "config was supplied but {name} doesn't import wasi:config, so it can't read it"
+        )),
+        _ => {}
+    }
+
+    // Providers are named from the template, and a name that's already taken, e.g. by the same
+    // package imported at another version, is disambiguated
+    let mut provider_names: Vec<String> = Vec::new();
+    for combined in &combined_interfaces {
+        let provider = naming::provider_name(
+            options,
+            name,
+            combined.namespace,
+            combined.package,
+            combined.version,
+            Some(combined.direction),
+        );
+        let taken: Vec<String> = std::iter::once(name.to_string())
+            .chain(provider_names.iter().cloned())
+            .collect();
+        provider_names.push(naming::unique_name(provider, combined.version, &taken));
+    }
+
+    for ((interface, direction), parsed) in names.iter().zip(parsed) {
+        let original = DirectionalInterface::parse(interface, *direction);
+        let (classification, reason) = match parsed {
+            None if original.is_some() => {
+                (Classification::Ignored, "ignored as requested".to_string())
+            }
+            None => (
+                Classification::Ignored,
+                "not a fully qualified interface (namespace:package/interface)".to_string(),
+            ),
+            Some(parsed) => match host_provided(&parsed, options) {
+                Some(reason) => (Classification::HostProvided, reason.to_string()),
+                None if parsed.is_type_only() => (
+                    Classification::TypeOnly,
+                    "only defines types for the other interfaces in its package".to_string(),
+                ),
+                None => {
+                    let index = combined_interfaces
+                        .iter()
+                        .position(|c| {
+                            c.namespace == parsed.namespace
+                                && c.package == parsed.package
+                                && c.version == parsed.version
+                                && c.direction == parsed.direction
+                        })
+                        .expect("linkable interfaces should have been combined");
+                    let combined = &combined_interfaces[index];
+                    let (classification, reason) =
+                        provider_classification(combined, &provider_names[index], options);
+                    match alias(combined, options) {
+                        Some(alias) => (classification, format!("aliased to {alias}, {reason}")),
+                        None => (classification, reason),
+                    }
+                }
+            },
+        };
+        plan.interfaces.push(InterfaceDecision {
+            component: name.to_string(),
+            interface: interface.to_string(),
+            direction: *direction,
+            classification,
+            reason,
+        });
+    }
+
+    for (combined, provider) in combined_interfaces.iter().zip(provider_names) {
+        let image = match provider_classification(combined, &provider, options).0 {
+            Classification::Provider { image, .. } | Classification::Custom { image, .. } => image,
+            _ => unreachable!("linkable interfaces are always backed by a provider"),
+        };
+        let aliased = aliased(combined, options);
+        // Backends given on the command line are validated when they're parsed, but ones from a
+        // settings file aren't
+        if let Some(backend) = package_option(&options.backends, aliased.namespace, aliased.package)
+            .filter(|_| catalog_provider(&aliased, options).is_none())
+            .filter(|_| {
+                provider_override(
+                    options,
+                    aliased.namespace,
+                    aliased.package,
+                    &aliased.interfaces,
+                )
+                .is_none()
+            })
+        {
+            let package = format!("{}:{}", aliased.namespace, aliased.package);
+            plan.warnings.push(match backends(&package).as_slice() {
+                [] => format!(
+                    "backend {backend} was requested for {package} but it has no known providers"
+                ),
+                available => format!(
+                    "unknown backend {backend} for {package}, expected one of: {}, so it's \
+                     linked to a placeholder image",
+                    available.join(", ")
+                ),
+            });
+        }
+        let interfaces: Vec<String> = combined.interfaces.iter().map(|i| i.to_string()).collect();
+        let provider_link_config = provider_link_config(name, combined, &provider, options);
+        let subscribed = !options.subscriptions.is_empty()
+            || package_option(&options.provider_config, aliased.namespace, aliased.package)
+                .is_some_and(|config| config.contains_key(SUBSCRIPTIONS_PROPERTY));
+        if is_messaging_handler(&aliased) && !subscribed {
+            let subscriptions = provider_link_config
+                .iter()
+                .find_map(|config| config.properties.get(SUBSCRIPTIONS_PROPERTY));
+            plan.warnings.push(match subscriptions {
+                Some(subscriptions) => format!(
+                    "{name} exports wasmcloud:messaging/handler but no subscriptions were given, \
+                     it will only receive messages on {subscriptions}"
+                ),
+                None => format!(
+                    "{name} exports wasmcloud:messaging/handler but no subscriptions were given, \
+                     it will not receive any messages"
+                ),
+            });
+        }
+        // Imports are linked from the component to the provider, while exports are linked from
+        // the provider to the component
+        let (source, target, source_config, target_config) = match combined.direction {
+            Direction::Import => (
+                name.to_string(),
+                provider.clone(),
+                vec![],
+                provider_link_config,
+            ),
+            Direction::Export => (
+                provider.clone(),
+                name.to_string(),
+                provider_link_config,
+                vec![],
+            ),
+        };
+        plan.links.push(LinkPlan {
+            source,
+            target,
+            namespace: combined.namespace.to_string(),
+            package: combined.package.to_string(),
+            interfaces: interfaces.clone(),
+            name: options.link_name.as_deref().map(|template| {
+                naming::render(
+                    template,
+                    name,
+                    combined.namespace,
+                    combined.package,
+                    combined.version,
+                    Some(combined.direction),
+                )
+            }),
+            source_config,
+            target_config,
+        });
+        plan.providers.push(ProviderPlan {
+            name: provider,
+            image,
+            namespace: combined.namespace.to_string(),
+            package: combined.package.to_string(),
+            version: combined.version.map(ToString::to_string),
+            direction: combined.direction,
+            interfaces,
+            config: Vec::new(),
+        });
+    }
+
+    consolidate_providers(&mut plan, options);
+    naming::disambiguate_links(&mut plan.links);
+    plan
+}
+
+/// Merges the providers for the imports and exports of the same package into a single provider
+/// component when they use the same image, since one instance can serve both directions. This
+/// is skipped for packages that should keep separate providers
+fn consolidate_providers(plan: &mut ApplicationPlan, options: &GenerateOptions) {
+    let pairs: Vec<(String, String, &ProviderPlan)> = plan
+        .providers
+        .iter()
+        .filter(|import| import.direction == Direction::Import)
+        .filter(|import| {
+            !options.separate_providers.iter().any(|package| {
+                *package == import.package
+                    || *package == format!("{}:{}", import.namespace, import.package)
+            })
+        })
+        .filter_map(|import| {
+            plan.providers
+                .iter()
+                .find(|export| {
+                    export.direction == Direction::Export
+                        && export.namespace == import.namespace
+                        && export.package == import.package
+                        && export.image == import.image
+                })
+                .map(|export| (import.name.clone(), export.name.clone(), import))
+        })
+        .collect();
+    let pairs: Vec<(String, String, String)> = pairs
+        .into_iter()
+        .map(|(import, export, provider)| {
+            let name = naming::provider_name(
+                options,
+                &plan.name,
+                &provider.namespace,
+                &provider.package,
+                provider.version.as_deref(),
+                None,
+            );
+            (import, export, name)
+        })
+        .collect();
+
+    for (import, export, name) in pairs {
+        let taken: Vec<String> = std::iter::once(plan.name.clone())
+            .chain(
+                plan.providers
+                    .iter()
+                    .map(|p| p.name.clone())
+                    .filter(|p| *p != import && *p != export),
+            )
+            .collect();
+        let name = naming::unique_name(name, None, &taken);
+        let Some(position) = plan.providers.iter().position(|p| p.name == export) else {
+            continue;
+        };
+        let export_provider = plan.providers.remove(position);
+        if let Some(provider) = plan.providers.iter_mut().find(|p| p.name == import) {
+            provider.name = name.clone();
+            provider.interfaces.extend(export_provider.interfaces);
+        }
+
+        for link in plan.links.iter_mut() {
+            if link.source == import || link.source == export {
+                link.source = name.clone();
+            }
+            if link.target == import || link.target == export {
+                link.target = name.clone();
+            }
+        }
+        for decision in plan.interfaces.iter_mut() {
+            if let Classification::Provider { provider, .. } = &mut decision.classification {
+                if *provider == import || *provider == export {
+                    *provider = name.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Classifies a combined interface that needs a capability provider as either backed by a well
+/// known provider from the [catalog](crate::catalog) or a custom one with a placeholder image
+fn provider_classification(
+    combined: &CombinedInterface,
+    provider: &str,
+    options: &GenerateOptions,
+) -> (Classification, String) {
+    let provider = provider.to_string();
+    let combined = &aliased(combined, options);
+    if let Some(image) = provider_override(
+        options,
+        combined.namespace,
+        combined.package,
+        &combined.interfaces,
+    ) {
+        let mut selection = "using the requested image".to_string();
+        let image = pin_image(image.clone(), options, &mut selection);
+        return (
+            Classification::Provider { provider, image },
+            format!(
+                "{}:{} is linked to a provider as requested, {selection}",
+                combined.namespace, combined.package
+            ),
+        );
+    }
+
+    match catalog_provider(combined, options) {
+        Some(catalog_provider) => {
+            let (image, mut selection) =
+                catalog_provider.image(combined.version, options.provider_images);
+            let image = pin_image(image, options, &mut selection);
+            (
+                Classification::Provider { provider, image },
+                format!(
+                    "{}:{} is implemented by the {} capability provider, {selection}",
+                    combined.namespace, combined.package, catalog_provider.backend
+                ),
+            )
+        }
+        None => (
+            Classification::Custom {
+                provider,
+                image: combined.placeholder_image(),
+            },
+            format!(
+                "{}:{} has no known capability provider, replace the placeholder image",
+                combined.namespace, combined.package
+            ),
+        ),
+    }
+}
+
+/// Pins an image to its digest if it's in the lockfile, noting it in the description of how the
+/// image was selected
+fn pin_image(image: String, options: &GenerateOptions, selection: &mut String) -> String {
+    match options.lockfile.as_ref().and_then(|l| l.pin(&image)) {
+        Some(pinned) => {
+            selection.push_str(", pinned to a digest by the lockfile");
+            pinned
+        }
+        None => image,
+    }
+}
+
+/// Returns true if an ignore rule matches the interface, either `namespace:package/interface` or
+/// `namespace:package`
+fn is_ignored(parsed: &DirectionalInterface, options: &GenerateOptions) -> bool {
+    let package = format!("{}:{}", parsed.namespace, parsed.package);
+    let interface = format!("{package}/{}", parsed.interface);
+    options
+        .ignore
+        .iter()
+        .any(|rule| *rule == package || *rule == interface)
+}
+
+/// The package an interface's package is an alias of, if there is an alias rule for it and no
+/// provider was requested for its own package
+fn alias<'a>(combined: &CombinedInterface, options: &'a GenerateOptions) -> Option<&'a String> {
+    options
+        .aliases
+        .get(&format!("{}:{}", combined.namespace, combined.package))
+        .filter(|_| {
+            provider_override(
+                options,
+                combined.namespace,
+                combined.package,
+                &combined.interfaces,
+            )
+            .is_none()
+        })
+}
+
+/// The interface to choose the provider, image and config for: the package the interface's
+/// package is an [alias] of, or otherwise the interface itself. The alias may include a version,
+/// e.g. `wasi:keyvalue@0.2.0-draft`, otherwise the original version is kept
+fn aliased<'a>(
+    combined: &CombinedInterface<'a>,
+    options: &'a GenerateOptions,
+) -> CombinedInterface<'a> {
+    let target = alias(combined, options).and_then(|alias| alias.split_once(':'));
+    let (namespace, package, version) = match target {
+        Some((namespace, package)) => match package.split_once('@') {
+            Some((package, version)) => (namespace, package, Some(version)),
+            None => (namespace, package, combined.version),
+        },
+        None => (combined.namespace, combined.package, combined.version),
+    };
+    CombinedInterface {
+        namespace,
+        package,
+        version,
+        direction: combined.direction,
+        interfaces: combined.interfaces.clone(),
+    }
+}
+
+/// The reason an interface is implemented by the host, unless a provider was requested for it
+fn host_provided(parsed: &DirectionalInterface, options: &GenerateOptions) -> Option<&'static str> {
+    parsed.host_provided().filter(|_| {
+        provider_override(
+            options,
+            parsed.namespace,
+            parsed.package,
+            &[parsed.interface],
+        )
+        .is_none()
+    })
+}
+
+/// The provider image requested for any of the given interfaces of a package, looked up by
+/// `namespace:package/interface` and then by `namespace:package`
+fn provider_override<'a>(
+    options: &'a GenerateOptions,
+    namespace: &str,
+    package: &str,
+    interfaces: &[&str],
+) -> Option<&'a String> {
+    interfaces
+        .iter()
+        .find_map(|interface| {
+            options
+                .provider_overrides
+                .get(&format!("{namespace}:{package}/{interface}"))
+        })
+        .or_else(|| {
+            options
+                .provider_overrides
+                .get(&format!("{namespace}:{package}"))
+        })
+}
+
+/// Finds the provider in the catalog for a combined interface, using the backend selected in the
+/// options for its package if there is one. Interfaces linked to a requested provider image
+/// never use the catalog
+fn catalog_provider(
+    combined: &CombinedInterface,
+    options: &GenerateOptions,
+) -> Option<&'static CatalogProvider> {
+    if provider_override(
+        options,
+        combined.namespace,
+        combined.package,
+        &combined.interfaces,
+    )
+    .is_some()
+    {
+        return None;
+    }
+    let backend = package_option(&options.backends, combined.namespace, combined.package);
+    find_provider(
+        combined.namespace,
+        combined.package,
+        combined.direction,
+        backend.map(String::as_str),
+    )
+}
+
+/// Builds the named config for the provider's side of a link, starting from the defaults in the
+/// catalog and applying any overrides from the options on top
+fn provider_link_config(
+    app: &str,
+    combined: &CombinedInterface,
+    provider: &str,
+    options: &GenerateOptions,
+) -> Vec<ConfigPlan> {
+    // A provider that was disambiguated, e.g. from the same package imported at another version,
+    // gives its config the same suffix so the two configs don't collide
+    let template = naming::provider_name(
+        options,
+        app,
+        combined.namespace,
+        combined.package,
+        combined.version,
+        Some(combined.direction),
+    );
+    let suffix = provider.strip_prefix(&template).unwrap_or_default();
+    let name = format!(
+        "{app}-{}-{}{suffix}",
+        combined.package,
+        combined.direction.as_str()
+    );
+    let combined = &aliased(combined, options);
+    let mut properties: BTreeMap<String, String> = catalog_provider(combined, options)
+        .map(|catalog| catalog.link_config(combined.direction))
+        .unwrap_or_default()
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.replace("{app}", app)))
+        .collect();
+    if is_messaging_handler(combined) && !options.subscriptions.is_empty() {
+        let subscriptions: Vec<String> = options
+            .subscriptions
+            .iter()
+            .map(|subscription| subscription.link_value())
+            .collect();
+        properties.insert(SUBSCRIPTIONS_PROPERTY.to_string(), subscriptions.join(","));
+    }
+    if let Some(overrides) = package_option(
+        &options.provider_config,
+        combined.namespace,
+        combined.package,
+    ) {
+        properties.extend(overrides.clone());
+    }
+
+    if properties.is_empty() {
+        return Vec::new();
+    }
+    vec![ConfigPlan { name, properties }]
+}
+
+/// Returns true if the combined interface is an export of the wasmCloud messaging handler, which
+/// only receives messages on the subjects its link subscribes to
+fn is_messaging_handler(combined: &CombinedInterface) -> bool {
+    combined.namespace == "wasmcloud"
+        && combined.package == "messaging"
+        && combined.direction == Direction::Export
+        && combined.interfaces.contains(&"handler")
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{plan_application, ApplicationPlan, Classification, ConfigPlan, LinkPlan};
+    use crate::catalog::ImageChannel;
+    use crate::lockfile::Lockfile;
+    use crate::{GenerateOptions, Subscription};
+
+    /// Plans the echo component with the given imports
+    fn plan(imports: &[&str], options: &GenerateOptions) -> ApplicationPlan {
+        plan_application(
+            "echo",
+            "file://./echo.wasm",
+            &imports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            &["wasi:http/incoming-handler@0.2.0".to_string()],
+            options,
+        )
+    }
+
+    /// The image of the provider serving a package
+    fn provider_image<'a>(plan: &'a ApplicationPlan, package: &str) -> &'a str {
+        plan.providers
+            .iter()
+            .find(|provider| provider.package == package)
+            .map(|provider| provider.image.as_str())
+            .expect("package should have a provider")
+    }
+
+    /// The link for a package
+    fn link<'a>(plan: &'a ApplicationPlan, package: &str) -> &'a LinkPlan {
+        plan.links
+            .iter()
+            .find(|link| link.package == package)
+            .expect("package should be linked")
+    }
+
+    /// A named config with the given properties
+    fn config(name: &str, properties: &[(&str, &str)]) -> Vec<ConfigPlan> {
+        vec![ConfigPlan {
+            name: name.to_string(),
+            properties: properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+        }]
+    }
+
+    #[test]
+    fn test_pin_image() {
+        let imports = [
+            "wasi:keyvalue/store@0.2.0-draft",
+            "wasi:blobstore/blobstore@0.2.0-draft",
+        ];
+        let mut options = GenerateOptions::default();
+        options.provider_overrides.insert(
+            "wasi:blobstore".to_string(),
+            "ghcr.io/acme/blobstore:1.0.0".to_string(),
+        );
+        let unlocked = plan(&imports, &options);
+        assert_eq!(
+            provider_image(&unlocked, "keyvalue"),
+            "ghcr.io/wasmcloud/keyvalue-redis:canary"
+        );
+
+        options.lockfile = Some(Lockfile {
+            images: [
+                ("ghcr.io/wasmcloud/keyvalue-redis:canary", "sha256:1111"),
+                ("ghcr.io/acme/blobstore:1.0.0", "sha256:2222"),
+            ]
+            .into_iter()
+            .map(|(image, digest)| (image.to_string(), digest.to_string()))
+            .collect(),
+        });
+        let pinned = plan(&imports, &options);
+        assert_eq!(
+            provider_image(&pinned, "keyvalue"),
+            "ghcr.io/wasmcloud/keyvalue-redis:canary@sha256:1111"
+        );
+        assert_eq!(
+            provider_image(&pinned, "blobstore"),
+            "ghcr.io/acme/blobstore:1.0.0@sha256:2222"
+        );
+        // Images that aren't in the lockfile are left alone
+        assert_eq!(
+            provider_image(&pinned, "http"),
+            provider_image(&unlocked, "http")
+        );
+
+        for decision in &pinned.interfaces {
+            let locked = matches!(
+                &decision.classification,
+                Classification::Provider { image, .. } if image.contains('@')
+            );
+            assert_eq!(
+                locked,
+                decision
+                    .reason
+                    .contains("pinned to a digest by the lockfile"),
+                "{}",
+                decision.interface
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        let imports = ["wasi:keyvalue/store@0.2.0-draft"];
+        let default = plan(&imports, &GenerateOptions::default());
+        assert_eq!(
+            provider_image(&default, "keyvalue"),
+            "ghcr.io/wasmcloud/keyvalue-redis:canary"
+        );
+
+        let mut options = GenerateOptions::default();
+        options
+            .backends
+            .insert("wasi:keyvalue".to_string(), "nats".to_string());
+        let nats = plan(&imports, &options);
+        assert_eq!(
+            provider_image(&nats, "keyvalue"),
+            "ghcr.io/wasmcloud/keyvalue-nats:canary"
+        );
+        assert!(nats.warnings.is_empty(), "{:?}", nats.warnings);
+        let decision = nats
+            .interfaces
+            .iter()
+            .find(|decision| decision.interface == imports[0])
+            .unwrap();
+        assert!(decision.reason.contains("nats"), "{}", decision.reason);
+
+        // The bare package name selects the backend too
+        options.backends.clear();
+        options
+            .backends
+            .insert("keyvalue".to_string(), "vault".to_string());
+        assert_eq!(
+            provider_image(&plan(&imports, &options), "keyvalue"),
+            "ghcr.io/wasmcloud/keyvalue-vault:canary"
+        );
+
+        options
+            .backends
+            .insert("keyvalue".to_string(), "etcd".to_string());
+        let unknown = plan(&imports, &options);
+        assert_eq!(
+            unknown.warnings,
+            vec![
+                "unknown backend etcd for wasi:keyvalue, expected one of: redis, nats, vault, so \
+                 it's linked to a placeholder image"
+                    .to_string()
+            ]
+        );
+        let image = provider_image(&unknown, "keyvalue");
+        assert!(!image.starts_with("ghcr.io/wasmcloud/"), "{image}");
+    }
+
+    #[test]
+    fn test_link_config() {
+        let imports = ["wasi:keyvalue/store@0.2.0-draft"];
+        let default = plan(&imports, &GenerateOptions::default());
+        let keyvalue = link(&default, "keyvalue");
+        assert_eq!(keyvalue.source, "echo");
+        assert!(keyvalue.source_config.is_empty());
+        assert_eq!(
+            keyvalue.target_config,
+            config("echo-keyvalue-target", &[("URL", "redis://127.0.0.1:6379")])
+        );
+        // The HTTP server's default address is on its side of the link, which is the source
+        assert_eq!(
+            link(&default, "http").source_config,
+            config("echo-http-source", &[("address", "0.0.0.0:8000")])
+        );
+
+        let mut options = GenerateOptions::default();
+        options.provider_config.insert(
+            "keyvalue".to_string(),
+            [("URL", "redis://redis:6379"), ("pool_size", "4")]
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+        options.provider_config.insert(
+            "wasi:blobstore".to_string(),
+            BTreeMap::from([("ROOT".to_string(), "/data".to_string())]),
+        );
+        let overridden = plan(
+            &[imports[0], "wasi:blobstore/blobstore@0.2.0-draft"],
+            &options,
+        );
+        assert_eq!(
+            link(&overridden, "keyvalue").target_config,
+            config(
+                "echo-keyvalue-target",
+                &[("URL", "redis://redis:6379"), ("pool_size", "4")]
+            )
+        );
+        assert_eq!(
+            link(&overridden, "blobstore").target_config,
+            config("echo-blobstore-target", &[("ROOT", "/data")])
+        );
+
+        // Two versions of one package get a config each, named after their own provider
+        let versions = plan(&[imports[0], "wasi:keyvalue/store@0.1.0"], &options);
+        let names: Vec<(&str, &str)> = versions
+            .links
+            .iter()
+            .filter(|link| link.package == "keyvalue")
+            .map(|link| (link.target.as_str(), link.target_config[0].name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("wasi:keyvalue-target", "echo-keyvalue-target"),
+                ("wasi:keyvalue-target-0.1.0", "echo-keyvalue-target-0.1.0"),
+            ]
+        );
+
+        // `{app}` in a default is replaced with the application name
+        let worker = plan_application(
+            "worker",
+            "file://./worker.wasm",
+            &[],
+            &["wasmcloud:messaging/handler@0.2.0".to_string()],
+            &GenerateOptions::default(),
+        );
+        let messaging = link(&worker, "messaging");
+        assert_eq!(messaging.target, "worker");
+        assert_eq!(
+            messaging.source_config,
+            config("worker-messaging-source", &[("subscriptions", "worker.>")])
+        );
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let worker = |options: &GenerateOptions| {
+            plan_application(
+                "worker",
+                "file://./worker.wasm",
+                &[],
+                &["wasmcloud:messaging/handler@0.2.0".to_string()],
+                options,
+            )
+        };
+
+        let default = worker(&GenerateOptions::default());
+        assert_eq!(
+            default.warnings,
+            vec![
+                "worker exports wasmcloud:messaging/handler but no subscriptions were given, it \
+                 will only receive messages on worker.>"
+                    .to_string()
+            ]
+        );
+
+        let mut options = GenerateOptions::default();
+        options.subscriptions = vec![
+            Subscription {
+                subject: "orders.>".to_string(),
+                queue: None,
+            },
+            Subscription {
+                subject: "jobs.*".to_string(),
+                queue: Some("workers".to_string()),
+            },
+        ];
+        let subscribed = worker(&options);
+        assert!(subscribed.warnings.is_empty(), "{:?}", subscribed.warnings);
+        assert_eq!(
+            link(&subscribed, "messaging").source_config,
+            config(
+                "worker-messaging-source",
+                &[("subscriptions", "orders.>,jobs.*|workers")]
+            )
+        );
+
+        // Subscriptions set as provider config count too
+        let mut options = GenerateOptions::default();
+        options.provider_config.insert(
+            "messaging".to_string(),
+            BTreeMap::from([("subscriptions".to_string(), "events.*".to_string())]),
+        );
+        let configured = worker(&options);
+        assert!(configured.warnings.is_empty(), "{:?}", configured.warnings);
+        assert_eq!(
+            link(&configured, "messaging").source_config,
+            config("worker-messaging-source", &[("subscriptions", "events.*")])
+        );
+
+        // Importing the messaging consumer isn't a subscription
+        let client = plan(
+            &["wasmcloud:messaging/consumer@0.2.0"],
+            &GenerateOptions::default(),
+        );
+        assert!(client.warnings.is_empty(), "{:?}", client.warnings);
+    }
+
+    #[test]
+    fn test_component_config() {
+        let mut options = GenerateOptions::default();
+        options
+            .config
+            .insert("LOG_LEVEL".to_string(), "debug".to_string());
+        options
+            .add_config_file("LOG_LEVEL = \"info\"\nGREETING = \"hello\"\n")
+            .unwrap();
+        assert!(options.add_config_file("RETRIES = 3").is_err());
+
+        // Flags win over the config file
+        let configured = plan(&["wasi:config/store@0.2.0-draft"], &options);
+        assert!(configured.warnings.is_empty(), "{:?}", configured.warnings);
+        assert_eq!(
+            configured.components[0].config,
+            config(
+                "echo-config",
+                &[("GREETING", "hello"), ("LOG_LEVEL", "debug")]
+            )
+        );
+        // The config is read through the host, so there's no provider for it
+        assert!(configured.providers.iter().all(|p| p.package != "config"));
+
+        let unconfigured = plan(&["wasi:keyvalue/store@0.2.0-draft"], &options);
+        assert!(unconfigured.components[0].config.is_empty());
+        assert_eq!(
+            unconfigured.warnings,
+            vec![
+                "config was supplied but echo doesn't import wasi:config, so it can't read it"
+                    .to_string()
+            ]
+        );
+
+        let nothing = plan(
+            &["wasi:config/store@0.2.0-draft"],
+            &GenerateOptions::default(),
+        );
+        assert!(nothing.components[0].config.is_empty());
+        assert!(nothing.warnings.is_empty(), "{:?}", nothing.warnings);
+    }
+
+    #[test]
+    fn test_override_host_provided() {
+        let imports = [
+            "wasi:logging/logging@0.1.0-draft",
+            "wasi:random/random@0.2.0",
+        ];
+        let default = plan(&imports, &GenerateOptions::default());
+        assert!(default.providers.iter().all(|p| p.package != "logging"));
+
+        let mut options = GenerateOptions::default();
+        options.provider_overrides.insert(
+            "wasi:logging".to_string(),
+            "ghcr.io/acme/logging:0.1.0".to_string(),
+        );
+        let overridden = plan(&imports, &options);
+        assert_eq!(
+            provider_image(&overridden, "logging"),
+            "ghcr.io/acme/logging:0.1.0"
+        );
+        let logging = link(&overridden, "logging");
+        assert_eq!(
+            (logging.source.as_str(), logging.namespace.as_str()),
+            ("echo", "wasi")
+        );
+        assert_eq!(logging.interfaces, vec!["logging".to_string()]);
+        let decision = |interface: &str| {
+            overridden
+                .interfaces
+                .iter()
+                .find(|decision| decision.interface == interface)
+                .unwrap()
+                .classification
+                .clone()
+        };
+        assert_eq!(
+            decision(imports[0]),
+            Classification::Provider {
+                provider: logging.target.clone(),
+                image: "ghcr.io/acme/logging:0.1.0".to_string(),
+            }
+        );
+        // Other host-provided packages are unaffected
+        assert_eq!(decision(imports[1]), Classification::HostProvided);
+
+        // An override of one interface only applies to that interface
+        let mut options = GenerateOptions::default();
+        options.provider_overrides.insert(
+            "wasi:random/insecure".to_string(),
+            "ghcr.io/acme/random:0.1.0".to_string(),
+        );
+        let unmatched = plan(&imports, &options);
+        assert!(unmatched.providers.iter().all(|p| p.package != "random"));
+    }
+
+    #[test]
+    fn test_secrets_host_provided() {
+        let imports = [
+            "wasmcloud:secrets/store@0.1.0-draft",
+            "wasmcloud:secrets/reveal@0.1.0-draft",
+        ];
+        let secrets = plan(&imports, &GenerateOptions::default());
+        assert!(secrets.providers.iter().all(|p| p.package != "secrets"));
+        assert!(secrets.links.iter().all(|l| l.package != "secrets"));
+        for import in imports {
+            let decision = secrets
+                .interfaces
+                .iter()
+                .find(|decision| decision.interface == import)
+                .unwrap();
+            assert_eq!(decision.classification, Classification::HostProvided);
+            assert!(decision.reason.contains("secrets"), "{}", decision.reason);
+        }
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut options = GenerateOptions::default();
+        options
+            .aliases
+            .insert("acme:kv".to_string(), "wasi:keyvalue".to_string());
+        options.provider_config.insert(
+            "keyvalue".to_string(),
+            BTreeMap::from([("URL".to_string(), "redis://redis:6379".to_string())]),
+        );
+        let aliased = plan(&["acme:kv/store@0.1.0", "acme:kv/atomics@0.1.0"], &options);
+
+        // The alias chooses the provider, image and config, but the link is still for the
+        // component's own package and interfaces
+        assert_eq!(
+            provider_image(&aliased, "kv"),
+            "ghcr.io/wasmcloud/keyvalue-redis:canary"
+        );
+        let kv = link(&aliased, "kv");
+        assert_eq!(kv.namespace, "acme");
+        assert_eq!(
+            kv.interfaces,
+            vec!["store".to_string(), "atomics".to_string()]
+        );
+        assert_eq!(
+            kv.target_config,
+            config("echo-kv-target", &[("URL", "redis://redis:6379")])
+        );
+        let decision = &aliased.interfaces[0];
+        assert_eq!(decision.interface, "acme:kv/store@0.1.0");
+        assert!(
+            decision.reason.starts_with("aliased to wasi:keyvalue, "),
+            "{}",
+            decision.reason
+        );
+        assert!(matches!(
+            decision.classification,
+            Classification::Provider { .. }
+        ));
+
+        // A version in the alias chooses the release for that version
+        options.provider_images = ImageChannel::Pinned;
+        options.aliases.insert(
+            "acme:kv".to_string(),
+            "wasi:keyvalue@0.2.0-draft".to_string(),
+        );
+        assert_eq!(
+            provider_image(&plan(&["acme:kv/store@0.1.0"], &options), "kv"),
+            "ghcr.io/wasmcloud/keyvalue-redis:0.24.0"
+        );
+
+        // A provider requested for the package itself wins over its alias
+        options
+            .provider_overrides
+            .insert("acme:kv".to_string(), "ghcr.io/acme/kv:1.0.0".to_string());
+        let overridden = plan(&["acme:kv/store@0.1.0"], &options);
+        assert_eq!(provider_image(&overridden, "kv"), "ghcr.io/acme/kv:1.0.0");
+        assert!(link(&overridden, "kv").target_config.is_empty());
+        assert!(!overridden.interfaces[0].reason.contains("aliased"));
+    }
+
+    #[test]
+    fn test_ignore() {
+        let imports = [
+            "acme:sidecar/tools@0.1.0",
+            "acme:sidecar/events@0.1.0",
+            "acme:cache/get@0.1.0",
+        ];
+        let classifications = |options: &GenerateOptions| {
+            plan(&imports, options)
+                .interfaces
+                .iter()
+                .map(|decision| decision.classification.label())
+                .collect::<Vec<_>>()
+        };
+
+        let mut options = GenerateOptions::default();
+        options.ignore = vec!["acme:sidecar/tools".to_string()];
+        assert_eq!(
+            classifications(&options),
+            vec!["ignored", "custom", "custom", "provider"]
+        );
+        let ignored = plan(&imports, &options);
+        assert_eq!(ignored.interfaces[0].reason, "ignored as requested");
+        assert_eq!(
+            link(&ignored, "sidecar").interfaces,
+            vec!["events".to_string()]
+        );
+
+        options.ignore = vec!["acme:sidecar".to_string()];
+        assert_eq!(
+            classifications(&options),
+            vec!["ignored", "ignored", "custom", "provider"]
+        );
+        assert!(plan(&imports, &options)
+            .links
+            .iter()
+            .all(|link| link.package != "sidecar"));
+
+        // Rules must name a whole package or interface
+        options.ignore = vec!["acme:side".to_string(), "acme:sidecar/tool".to_string()];
+        assert_eq!(
+            classifications(&options),
+            vec!["custom", "custom", "custom", "provider"]
+        );
+    }
+
+    #[test]
+    fn test_consolidate_providers() {
+        let keyvalue = |options: &GenerateOptions| {
+            plan_application(
+                "echo",
+                "file://./echo.wasm",
+                &["wasi:keyvalue/store@0.2.0-draft".to_string()],
+                &["wasi:keyvalue/watcher@0.2.0-draft".to_string()],
+                options,
+            )
+        };
+        let names = |plan: &ApplicationPlan| {
+            plan.providers
+                .iter()
+                .map(|provider| provider.name.clone())
+                .collect::<Vec<_>>()
+        };
+        let links = |plan: &ApplicationPlan| {
+            plan.links
+                .iter()
+                .map(|link| (link.source.clone(), link.target.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        // One provider serves both the imports and exports of a package
+        let consolidated = keyvalue(&GenerateOptions::default());
+        assert_eq!(names(&consolidated), vec!["wasi:keyvalue"]);
+        assert_eq!(
+            consolidated.providers[0].interfaces,
+            vec!["store".to_string(), "watcher".to_string()]
+        );
+        assert_eq!(
+            links(&consolidated),
+            vec![
+                ("echo".to_string(), "wasi:keyvalue".to_string()),
+                ("wasi:keyvalue".to_string(), "echo".to_string()),
+            ]
+        );
+        assert!(consolidated.interfaces.iter().all(|decision| matches!(
+            &decision.classification,
+            Classification::Provider { provider, .. } if provider == "wasi:keyvalue"
+        )));
+
+        let mut options = GenerateOptions::default();
+        options.separate_providers = vec!["wasi:keyvalue".to_string()];
+        let separate = keyvalue(&options);
+        assert_eq!(
+            names(&separate),
+            vec!["wasi:keyvalue-target", "wasi:keyvalue-source"]
+        );
+        assert_eq!(
+            links(&separate),
+            vec![
+                ("echo".to_string(), "wasi:keyvalue-target".to_string()),
+                ("wasi:keyvalue-source".to_string(), "echo".to_string()),
+            ]
+        );
+
+        // Providers with different images are never merged
+        let mut options = GenerateOptions::default();
+        options.provider_overrides.insert(
+            "wasi:keyvalue/watcher".to_string(),
+            "ghcr.io/acme/watcher:0.1.0".to_string(),
+        );
+        assert_eq!(
+            names(&keyvalue(&options)),
+            vec!["wasi:keyvalue-target", "wasi:keyvalue-source"]
+        );
+    }
+}
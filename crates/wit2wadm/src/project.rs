@@ -0,0 +1,191 @@
+//! Defaults from a wasmCloud project's `wasmcloud.toml`, so that running wit2wadm in a project
+//! directory without an input converts the component the project builds, named and versioned
+//! after the project.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use serde::Deserialize;
+
+/// The name of the file describing a wasmCloud project
+pub const PROJECT_FILE: &str = "wasmcloud.toml";
+
+/// The parts of `wasmcloud.toml` wit2wadm uses. Everything else in the file is ignored
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Project {
+    /// The name of the project
+    pub name: Option<String>,
+    /// The version of the project
+    pub version: Option<String>,
+    /// The language the project is written in, e.g. `rust`
+    pub language: Option<String>,
+    /// The type of the project, `component` or `provider`
+    #[serde(rename = "type")]
+    pub project_type: Option<String>,
+    /// The path to the project, relative to `wasmcloud.toml`
+    pub path: Option<PathBuf>,
+    /// The WIT directory of the project. Defaults to `wit`
+    pub wit: Option<PathBuf>,
+    /// The directory built artifacts are written to. Defaults to `build`
+    pub build: Option<PathBuf>,
+    /// The name of the built wasm file, without its extension. Defaults to the project name
+    pub wasm_bin_name: Option<String>,
+    /// The `[component]` table, or the `[actor]` table of older projects
+    #[serde(default, alias = "actor")]
+    pub component: ProjectComponent,
+}
+
+/// The `[component]` table of `wasmcloud.toml`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ProjectComponent {
+    /// The WIT world the component targets
+    pub wit_world: Option<String>,
+    /// The target the component is built for, e.g. `wasm32-wasi-preview2`
+    pub wasm_target: Option<String>,
+    /// The path to the built component
+    pub build_artifact: Option<PathBuf>,
+    /// The path the signed component is written to
+    pub destination: Option<PathBuf>,
+}
+
+/// What a project converts to a manifest from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProjectInput {
+    /// The component the project built
+    Component(PathBuf),
+    /// The project's WIT directory and world, when the component hasn't been built yet
+    Wit { folder: PathBuf, world: String },
+}
+
+impl Project {
+    /// Parses the contents of a `wasmcloud.toml`
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        toml::from_str(contents).context("failed to parse wasmcloud.toml")
+    }
+
+    /// The paths the project's built component may be at, most specific first, relative to the
+    /// directory containing `wasmcloud.toml`
+    pub fn component_paths(&self) -> Vec<PathBuf> {
+        let root = self.root();
+        let build = root.join(self.build.as_deref().unwrap_or(Path::new("build")));
+        let mut paths: Vec<PathBuf> = [&self.component.destination, &self.component.build_artifact]
+            .into_iter()
+            .flatten()
+            .map(|path| root.join(path))
+            .collect();
+        if let Some(bin_name) = self.bin_name() {
+            paths.push(build.join(format!("{bin_name}_s.wasm")));
+            paths.push(build.join(format!("{bin_name}.wasm")));
+        }
+        paths
+    }
+
+    /// The project's WIT directory, relative to the directory containing `wasmcloud.toml`
+    pub fn wit_dir(&self) -> PathBuf {
+        self.root()
+            .join(self.wit.as_deref().unwrap_or(Path::new("wit")))
+    }
+
+    /// Finds what to convert for a project in `dir`: the first of its built components that
+    /// exists, or otherwise its WIT directory and world
+    pub fn input(
+        &self,
+        dir: &Path,
+        exists: impl Fn(&Path) -> bool,
+    ) -> anyhow::Result<ProjectInput> {
+        if self.project_type.as_deref() == Some("provider") {
+            bail!("{PROJECT_FILE} describes a provider, not a component");
+        }
+        let components = self.component_paths();
+        if let Some(path) = components
+            .iter()
+            .map(|path| dir.join(path))
+            .find(|path| exists(path))
+        {
+            return Ok(ProjectInput::Component(path));
+        }
+
+        let folder = dir.join(self.wit_dir());
+        match &self.component.wit_world {
+            Some(world) if exists(&folder) => Ok(ProjectInput::Wit {
+                folder,
+                world: world.clone(),
+            }),
+            _ => bail!(
+                "no built component found for the project in {}, build it first or pass its \
+                 path. Looked for {}",
+                dir.display(),
+                components
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    fn root(&self) -> PathBuf {
+        self.path.clone().unwrap_or_default()
+    }
+
+    /// The name of the built wasm file. Rust replaces dashes in crate names with underscores
+    fn bin_name(&self) -> Option<String> {
+        if let Some(bin_name) = &self.wasm_bin_name {
+            return Some(bin_name.clone());
+        }
+        let name = self.name.as_deref()?;
+        Some(match self.language.as_deref() {
+            Some("rust") => name.replace('-', "_"),
+            _ => name.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{Project, ProjectInput};
+
+    #[test]
+    fn test_project_input() {
+        let project = Project::parse(
+            r#"
+            name = "http-hello"
+            language = "rust"
+            type = "component"
+            version = "0.2.0"
+
+            [component]
+            wit_world = "hello"
+            wasm_target = "wasm32-wasi-preview2"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(project.version.as_deref(), Some("0.2.0"));
+
+        let dir = Path::new("hello");
+        let built = project
+            .input(dir, |path| {
+                path == Path::new("hello/build/http_hello_s.wasm")
+            })
+            .unwrap();
+        assert_eq!(
+            built,
+            ProjectInput::Component(PathBuf::from("hello/build/http_hello_s.wasm"))
+        );
+
+        let unbuilt = project
+            .input(dir, |path| path == Path::new("hello/wit"))
+            .unwrap();
+        assert_eq!(
+            unbuilt,
+            ProjectInput::Wit {
+                folder: PathBuf::from("hello/wit"),
+                world: "hello".to_string(),
+            }
+        );
+
+        assert!(project.input(dir, |_| false).is_err());
+    }
+}
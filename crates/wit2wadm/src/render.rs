@@ -0,0 +1,178 @@
+//! Rendering manifests for output. Several manifests render as a stream of YAML documents
+//! separated by `---`, or as a JSON array.
+
+use std::io::Write as _;
+use std::path::Path;
+
+use anyhow::{bail, Context as _};
+use serde::{Deserialize, Serialize};
+use wadm::model::Manifest;
+
+/// The format manifests are rendered in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// YAML, the format wadm manifests are usually written in
+    #[default]
+    Yaml,
+    /// Compact JSON
+    Json,
+    /// Pretty printed JSON
+    JsonPretty,
+}
+
+impl OutputFormat {
+    /// The file extension for files in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Json | OutputFormat::JsonPretty => "json",
+        }
+    }
+}
+
+/// The name of the file a manifest is written to in an output directory, e.g. `echo.yaml`
+pub fn manifest_file_name(manifest: &Manifest, format: OutputFormat) -> String {
+    format!("{}.{}", manifest.metadata.name, format.extension())
+}
+
+/// Renders manifests in the given format. A single manifest renders as one document, while
+/// several render as a multi-document YAML stream or a JSON array
+pub fn render_manifests(manifests: &[Manifest], format: OutputFormat) -> anyhow::Result<String> {
+    let json = match (format, manifests) {
+        (OutputFormat::Yaml, _) => {
+            let documents = manifests
+                .iter()
+                .map(serde_yaml::to_string)
+                .collect::<Result<Vec<_>, _>>()
+                .context("failed to serialize manifest to YAML")?;
+            return Ok(documents.join("---\n"));
+        }
+        (OutputFormat::Json, [manifest]) => serde_json::to_string(manifest),
+        (OutputFormat::Json, _) => serde_json::to_string(manifests),
+        (OutputFormat::JsonPretty, [manifest]) => serde_json::to_string_pretty(manifest),
+        (OutputFormat::JsonPretty, _) => serde_json::to_string_pretty(manifests),
+    };
+    json.context("failed to serialize manifest to JSON")
+}
+
+/// Writes a file atomically by writing to a temporary file next to it and renaming it into place,
+/// so readers never see a partially written manifest. An existing file is only replaced when
+/// `overwrite` is set
+pub fn write_atomic(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+    overwrite: bool,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if !overwrite && path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} is not a file path", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let written = std::fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temp, path));
+    if let Err(err) = written {
+        let _ = std::fs::remove_file(&temp);
+        return Err(err).with_context(|| format!("failed to write {}", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize as _;
+    use wadm::model::Manifest;
+
+    use super::{render_manifests, write_atomic, OutputFormat};
+    use crate::manifest::base_manifest;
+    use crate::test_dir;
+
+    fn names(manifests: &[Manifest]) -> Vec<&str> {
+        manifests.iter().map(|m| m.metadata.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_render_manifests() {
+        let echo = base_manifest("echo", "An Echo Application", "v0.1.0");
+        let api = base_manifest("api", "An API", "v0.2.0");
+        let both = [echo, api];
+
+        // Several manifests render as YAML documents separated by `---`
+        let yaml = render_manifests(&both, OutputFormat::Yaml).unwrap();
+        assert_eq!(yaml.matches("---\n").count(), 1, "{yaml}");
+        let documents = serde_yaml::Deserializer::from_str(&yaml)
+            .map(Manifest::deserialize)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(names(&documents), vec!["echo", "api"]);
+        let yaml = render_manifests(&both[..1], OutputFormat::Yaml).unwrap();
+        assert!(!yaml.contains("---"), "{yaml}");
+        let document: Manifest = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(names(&[document]), vec!["echo"]);
+
+        // Or as a JSON array, compact or pretty printed
+        for format in [OutputFormat::Json, OutputFormat::JsonPretty] {
+            let json = render_manifests(&both, format).unwrap();
+            assert!(json.starts_with('['), "{json}");
+            let array: Vec<Manifest> = serde_json::from_str(&json).unwrap();
+            assert_eq!(names(&array), vec!["echo", "api"]);
+        }
+
+        // A single manifest is a JSON object rather than an array of one
+        let json = render_manifests(&both[..1], OutputFormat::Json).unwrap();
+        assert!(json.starts_with('{') && !json.contains('\n'), "{json}");
+        let object: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(names(&[object]), vec!["echo"]);
+        let pretty = render_manifests(&both[..1], OutputFormat::JsonPretty).unwrap();
+        assert!(pretty.starts_with("{\n"), "{pretty}");
+        let object: Manifest = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(names(&[object]), vec!["echo"]);
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = test_dir("write-atomic");
+        let path = dir.join("echo.yaml");
+        let files = || {
+            let mut files: Vec<String> = std::fs::read_dir(&dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            files.sort();
+            files
+        };
+
+        write_atomic(&path, "first\n", false).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\n");
+
+        // An existing file is left alone unless overwriting was asked for
+        let err = write_atomic(&path, "second\n", false).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\n");
+
+        write_atomic(&path, "second\n", true).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second\n");
+        // The temporary file was renamed into place
+        assert_eq!(files(), vec!["echo.yaml"]);
+
+        // A failed write doesn't leave a temporary file behind
+        assert!(write_atomic(dir.join("missing").join("echo.yaml"), "", true).is_err());
+        std::fs::create_dir(dir.join("manifests")).unwrap();
+        std::fs::write(dir.join("manifests").join("echo.yaml"), "").unwrap();
+        assert!(write_atomic(dir.join("manifests"), "", true).is_err());
+        assert_eq!(files(), vec!["echo.yaml", "manifests"]);
+    }
+}
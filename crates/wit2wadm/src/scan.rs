@@ -0,0 +1,166 @@
+//! A fast, metadata-only scan of a component binary.
+//!
+//! Fully decoding a component with [wit_component::decode] validates the entire binary, which
+//! for large components (e.g. Python or JavaScript components embedding an interpreter) takes
+//! seconds and a lot of memory. Generating a manifest only needs the names of the outer
+//! component's imports and exports, so this reads the top level import and export sections and
+//! skips over every nested core module and component without looking inside them.
+
+use anyhow::{bail, Context as _};
+use wasmparser::{Chunk, ComponentExternalKind, ComponentTypeRef, Encoding, Parser, Payload};
+
+/// What a WebAssembly binary contains, as told by [sniff]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WasmKind {
+    /// A component, with the imports and exports found while scanning it
+    Component(ComponentInterfaces),
+    /// A binary-encoded WIT package, i.e. a component that only exports types
+    WitPackage,
+    /// A core module, which describes the component it's built for in custom sections
+    CoreModule,
+}
+
+/// Tells what kind of WebAssembly binary the bytes are without decoding them, for inputs like
+/// stdin that don't come with a file name. Components are scanned with [scan_component] to tell
+/// them apart from WIT packages, so their imports and exports are returned too
+pub fn sniff(bytes: &[u8]) -> anyhow::Result<WasmKind> {
+    if !bytes.starts_with(b"\0asm") {
+        bail!("input is not a WebAssembly binary");
+    }
+    match Parser::new(0)
+        .parse(bytes, true)
+        .context("failed to parse WebAssembly header")?
+    {
+        Chunk::Parsed {
+            payload:
+                Payload::Version {
+                    encoding: Encoding::Module,
+                    ..
+                },
+            ..
+        } => Ok(WasmKind::CoreModule),
+        Chunk::Parsed {
+            payload:
+                Payload::Version {
+                    encoding: Encoding::Component,
+                    ..
+                },
+            ..
+        } => match scan_component(bytes)? {
+            Some(interfaces) => Ok(WasmKind::Component(interfaces)),
+            None => Ok(WasmKind::WitPackage),
+        },
+        _ => bail!("failed to parse WebAssembly header"),
+    }
+}
+
+/// The names of the imports and exports of a component's outer component type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentInterfaces {
+    pub imports: Vec<String>,
+    pub exports: Vec<String>,
+}
+
+/// Scans the outer import and export sections of a component, returning `None` if the bytes are
+/// not a component that can be scanned this way (e.g. a core module or a binary-encoded WIT
+/// package) and the caller should fall back to [wit_component::decode]
+pub fn scan_component(bytes: &[u8]) -> anyhow::Result<Option<ComponentInterfaces>> {
+    let mut parser = Parser::new(0);
+    let mut offset = 0;
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+    let mut only_type_exports = true;
+
+    loop {
+        let payload = match parser
+            .parse(&bytes[offset..], true)
+            .context("failed to parse component")?
+        {
+            Chunk::Parsed { consumed, payload } => {
+                offset += consumed;
+                payload
+            }
+            // This state isn't possible with `eof = true`
+            Chunk::NeedMoreData(_) => bail!("unexpected end of component"),
+        };
+
+        match payload {
+            Payload::Version { encoding, .. } if encoding != Encoding::Component => {
+                return Ok(None);
+            }
+            Payload::ComponentImportSection(reader) => {
+                for import in reader {
+                    let import = import.context("failed to read component import")?;
+                    if !matches!(import.ty, ComponentTypeRef::Type(..)) {
+                        imports.push(import.name.0.to_string());
+                    }
+                }
+            }
+            Payload::ComponentExportSection(reader) => {
+                for export in reader {
+                    let export = export.context("failed to read component export")?;
+                    if export.kind != ComponentExternalKind::Type {
+                        only_type_exports = false;
+                        exports.push(export.name.0.to_string());
+                    }
+                }
+            }
+            // Nested modules and components are skipped entirely, the parser has already
+            // advanced past them so we only need to move our view of the bytes
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => {
+                if let Some((_, range)) = payload.as_section() {
+                    offset = range.end;
+                }
+            }
+            Payload::End(_) => break,
+            _ => {}
+        }
+    }
+
+    // Binary-encoded WIT packages are components that only export types
+    if only_type_exports && imports.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(ComponentInterfaces { imports, exports }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{scan_component, sniff, ComponentInterfaces, WasmKind};
+    use crate::test_util::{component, core_module};
+    use crate::world_interfaces;
+
+    #[test]
+    fn test_scan_matches_decode() {
+        let module = core_module("server");
+        let component = component();
+
+        let scanned = scan_component(&component)
+            .unwrap()
+            .expect("component should be scannable");
+        assert_eq!(scanned.imports, vec!["wit2wadm:test/store"]);
+        assert_eq!(scanned.exports, vec!["wit2wadm:test/handler"]);
+
+        // The scan finds the same interfaces as decoding the whole component
+        let wit_component::DecodedWasm::Component(resolve, world) =
+            wit_component::decode(&component).unwrap()
+        else {
+            panic!("expected a component");
+        };
+        let decoded = world_interfaces(&resolve, &resolve.worlds[world]);
+        assert_eq!(scanned, decoded);
+
+        assert!(scan_component(&module).unwrap().is_none());
+
+        assert_eq!(
+            sniff(&component).unwrap(),
+            WasmKind::Component(ComponentInterfaces {
+                imports: vec!["wit2wadm:test/store".to_string()],
+                exports: vec!["wit2wadm:test/handler".to_string()],
+            })
+        );
+        assert_eq!(sniff(&module).unwrap(), WasmKind::CoreModule);
+        assert!(sniff(b"package wit2wadm:test;").is_err());
+    }
+}
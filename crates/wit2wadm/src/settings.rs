@@ -0,0 +1,205 @@
+//! The `wit2wadm.toml` settings file, which holds the options for generating a manifest so they
+//! don't have to be repeated as flags on every invocation. The same file is read by the CLI, the
+//! wash plugin and the wit2wadm component, and flags given to the CLI override it.
+//!
+//! Generation options use the same keys as [GenerateOptions] at the top level of the file,
+//! next to the settings for the application itself and the paths of the files the CLI reads.
+//! Unknown keys are rejected, so a misspelled option isn't silently ignored:
+//!
+//! ```toml
+//! name = "shop"
+//! version = "v1.2.0"
+//! strict = true
+//!
+//! [backends]
+//! keyvalue = "nats"
+//!
+//! [provider-config.http]
+//! address = "0.0.0.0:8080"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+
+use crate::catalog::ImageChannel;
+use crate::compose::HttpRouting;
+use crate::render::OutputFormat;
+use crate::{GenerateOptions, Subscription};
+
+/// The name of the settings file, read from the current directory when it exists
+pub const SETTINGS_FILE: &str = "wit2wadm.toml";
+
+/// Everything that can be set in `wit2wadm.toml`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Settings {
+    /// The name of the application
+    pub name: Option<String>,
+    /// The description of the application
+    pub description: Option<String>,
+    /// The version of the application
+    pub version: Option<String>,
+    /// The image of the component
+    pub image: Option<String>,
+    /// The world to convert from a WIT folder or package
+    pub world: Option<String>,
+    /// The format manifests are rendered in
+    pub format: Option<OutputFormat>,
+    /// Fail when the manifest still contains placeholders, see [strict](crate::strict)
+    pub strict: Option<bool>,
+    /// The lockfile used to pin provider images to digests, see [lockfile](crate::lockfile)
+    pub lockfile: Option<PathBuf>,
+    /// A TOML file of config for components that import `wasi:config`
+    pub config_file: Option<PathBuf>,
+    /// A TOML file with a table of provider link config per package
+    pub provider_config_file: Option<PathBuf>,
+    /// The options for generating the manifest
+    pub options: GenerateOptions,
+}
+
+impl Settings {
+    /// Parses the contents of a settings file, rejecting keys it doesn't know
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let file: SettingsFile = toml::from_str(contents).context("invalid settings file")?;
+        Ok(file.into())
+    }
+
+    /// Resolves the relative file paths in these settings against `dir`, the directory the
+    /// settings file was read from, so a file passed to `--settings` finds its neighbours
+    pub fn resolve_paths(&mut self, dir: &Path) {
+        for path in [
+            &mut self.lockfile,
+            &mut self.config_file,
+            &mut self.provider_config_file,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = dir.join(&*path);
+        }
+    }
+}
+
+/// The keys of `wit2wadm.toml`. The generation options are listed one by one rather than
+/// flattening [GenerateOptions] in, since serde can't reject unknown keys next to a flattened
+/// struct
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct SettingsFile {
+    name: Option<String>,
+    description: Option<String>,
+    version: Option<String>,
+    image: Option<String>,
+    world: Option<String>,
+    format: Option<OutputFormat>,
+    strict: Option<bool>,
+    lockfile: Option<PathBuf>,
+    config_file: Option<PathBuf>,
+    provider_config_file: Option<PathBuf>,
+    provider_images: ImageChannel,
+    backends: BTreeMap<String, String>,
+    provider_overrides: BTreeMap<String, String>,
+    provider_name: Option<String>,
+    link_name: Option<String>,
+    separate_providers: Vec<String>,
+    ignore: Vec<String>,
+    aliases: BTreeMap<String, String>,
+    provider_config: BTreeMap<String, BTreeMap<String, String>>,
+    http_routing: HttpRouting,
+    http_routes: BTreeMap<String, String>,
+    subscriptions: Vec<Subscription>,
+    config: BTreeMap<String, String>,
+}
+
+impl From<SettingsFile> for Settings {
+    fn from(file: SettingsFile) -> Self {
+        Settings {
+            name: file.name,
+            description: file.description,
+            version: file.version,
+            image: file.image,
+            world: file.world,
+            format: file.format,
+            strict: file.strict,
+            lockfile: file.lockfile,
+            config_file: file.config_file,
+            provider_config_file: file.provider_config_file,
+            options: GenerateOptions {
+                provider_images: file.provider_images,
+                backends: file.backends,
+                provider_overrides: file.provider_overrides,
+                provider_name: file.provider_name,
+                link_name: file.link_name,
+                separate_providers: file.separate_providers,
+                ignore: file.ignore,
+                aliases: file.aliases,
+                provider_config: file.provider_config,
+                http_routing: file.http_routing,
+                http_routes: file.http_routes,
+                subscriptions: file.subscriptions,
+                config: file.config,
+                lockfile: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use super::Settings;
+    use crate::catalog::ImageChannel;
+    use crate::render::OutputFormat;
+
+    #[test]
+    fn test_parse_settings() {
+        let settings = Settings::parse(
+            r#"
+            name = "shop"
+            format = "json-pretty"
+            strict = true
+            lockfile = "locks/wit2wadm.lock"
+            provider-images = "latest"
+            ignore = ["acme:sidecar"]
+
+            [backends]
+            keyvalue = "nats"
+
+            [provider-config.http]
+            address = "0.0.0.0:8080"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.name.as_deref(), Some("shop"));
+        assert_eq!(settings.format, Some(OutputFormat::JsonPretty));
+        assert_eq!(settings.strict, Some(true));
+        assert_eq!(settings.options.provider_images, ImageChannel::Latest);
+        assert_eq!(settings.options.ignore, vec!["acme:sidecar"]);
+        assert_eq!(settings.options.backends["keyvalue"], "nats");
+        assert_eq!(
+            settings.options.provider_config["http"],
+            BTreeMap::from([("address".to_string(), "0.0.0.0:8080".to_string())])
+        );
+        assert_eq!(
+            settings.lockfile,
+            Some(PathBuf::from("locks/wit2wadm.lock"))
+        );
+        assert_eq!(settings.config_file, None);
+
+        assert_eq!(Settings::parse("").unwrap(), Settings::default());
+
+        // Misspelled keys are rejected rather than silently ignored, at the top level and in
+        // tables
+        let err = Settings::parse("provider-image = \"latest\"").unwrap_err();
+        assert!(format!("{err:#}").contains("provider-image"), "{err:#}");
+        assert!(
+            Settings::parse("[[subscriptions]]\nsubject = \"orders.>\"\nqeue = \"workers\"")
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! The well known providers of an application as a separate "shared" wadm application, for
+//! lattices that run providers like the HTTP server once instead of in every application.
+//!
+//! The wadm manifest model wit2wadm builds on can't reference a component of another application,
+//! so the application itself keeps its own providers and links. The shared application is only
+//! generated next to it, ready to be deployed once for the lattice.
+
+use wadm::model::Manifest;
+
+use crate::plan::{ApplicationPlan, Classification, ProviderPlan};
+
+/// The annotation that marks a wadm application as shared with other applications
+pub const SHARED_ANNOTATION: &str = "experimental.wasmcloud.dev/shared";
+
+/// The description of the shared application
+pub const SHARED_DESCRIPTION: &str = "Shared capability providers";
+
+/// Plans the shared application with the given name, holding a copy of the plan's well known
+/// providers. The packages may be written with or without their namespace, and when none are
+/// given every well known provider is shared. Custom providers with placeholder images never are
+pub fn shared_providers(
+    plan: &ApplicationPlan,
+    shared_app: &str,
+    packages: &[String],
+) -> ApplicationPlan {
+    let well_known: Vec<&String> = plan
+        .interfaces
+        .iter()
+        .filter_map(|decision| match &decision.classification {
+            Classification::Provider { provider, .. } => Some(provider),
+            _ => None,
+        })
+        .collect();
+    let is_shared = |provider: &ProviderPlan| {
+        well_known.contains(&&provider.name)
+            && (packages.is_empty()
+                || packages.iter().any(|package| {
+                    *package == provider.package
+                        || *package == format!("{}:{}", provider.namespace, provider.package)
+                }))
+    };
+
+    ApplicationPlan {
+        name: shared_app.to_string(),
+        components: Vec::new(),
+        interfaces: Vec::new(),
+        providers: plan
+            .providers
+            .iter()
+            .filter(|p| is_shared(p))
+            .cloned()
+            .collect(),
+        links: Vec::new(),
+        warnings: Vec::new(),
+    }
+}
+
+/// Marks a manifest rendered from the plan returned by [shared_providers] as a shared application
+pub fn mark_shared(manifest: &mut Manifest) {
+    manifest
+        .metadata
+        .annotations
+        .insert(SHARED_ANNOTATION.to_string(), "true".to_string());
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mark_shared, shared_providers, SHARED_ANNOTATION, SHARED_DESCRIPTION};
+    use crate::plan::plan_application;
+    use crate::{plan_to_wadm, GenerateOptions};
+
+    #[test]
+    fn test_shared_providers() {
+        let plan = plan_application(
+            "echo",
+            "file://./echo.wasm",
+            &[
+                "wasi:keyvalue/store@0.2.0-draft".to_string(),
+                "acme:sidecar/tools@0.1.0".to_string(),
+            ],
+            &["wasi:http/incoming-handler@0.2.0".to_string()],
+            &GenerateOptions::default(),
+        );
+
+        // Every well known provider is shared by default, but not the custom one
+        let shared = shared_providers(&plan, "providers", &[]);
+        let names: Vec<&str> = shared.providers.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["wasi:keyvalue-target", "wasi:http-source"]);
+        assert!(shared.components.is_empty() && shared.links.is_empty());
+
+        let shared = shared_providers(&plan, "providers", &["wasi:http".to_string()]);
+        let names: Vec<&str> = shared.providers.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["wasi:http-source"]);
+
+        let mut manifest = plan_to_wadm(&shared, SHARED_DESCRIPTION, "v0.1.0");
+        mark_shared(&mut manifest);
+        assert_eq!(manifest.metadata.name, "providers");
+        assert_eq!(manifest.metadata.annotations[SHARED_ANNOTATION], "true");
+        assert_eq!(manifest.spec.components.len(), 1);
+    }
+}
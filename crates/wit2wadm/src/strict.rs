@@ -0,0 +1,318 @@
+//! Strict checks for generated manifests. wit2wadm fills in placeholders wherever it can't know
+//! the right value (custom provider images, the application image, the application name) and
+//! references floating provider tags like `canary`, all of which deploy fine but break later.
+//! Providers that nothing links to and links to components that aren't in the manifest deploy
+//! too, but never work. These checks let CI reject a manifest that still contains any of them.
+
+use std::fmt;
+
+use wadm::model::{Manifest, Properties, TraitProperty};
+
+/// Prefix of the image generated for custom interfaces without a known provider
+pub const PLACEHOLDER_IMAGE_PREFIX: &str = "REGISTRY-IMAGE/";
+/// Images used when no image is supplied for the component
+pub const PLACEHOLDER_IMAGES: [&str; 2] = ["APPLICATION_IMAGE", "myregistry.io/"];
+/// Names used when no name is supplied for the application
+pub const PLACEHOLDER_NAMES: [&str; 2] = ["APPLICATION_NAME", "wit2wadm"];
+/// Tags that are moved to new builds, so they don't identify an image
+pub const FLOATING_TAGS: [&str; 2] = ["canary", "latest"];
+
+/// A single placeholder or missing link found in a manifest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Placeholder {
+    /// A component or provider image that is a placeholder
+    Image { component: String, image: String },
+    /// A component or provider image with a floating tag, i.e. `canary`, `latest` or no tag
+    FloatingTag { component: String, image: String },
+    /// The application name is a default
+    Name { name: String },
+    /// A provider that doesn't link to anything and isn't linked to
+    UnlinkedProvider { component: String },
+    /// A link to a component that isn't in the manifest
+    MissingLinkTarget { component: String, target: String },
+}
+
+impl fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Placeholder::Image { component, image } => {
+                write!(f, "component {component} has placeholder image {image}")
+            }
+            Placeholder::FloatingTag { component, image } => {
+                write!(f, "component {component} uses floating image tag {image}")
+            }
+            Placeholder::Name { name } => write!(f, "application has default name {name}"),
+            Placeholder::UnlinkedProvider { component } => {
+                write!(f, "provider {component} isn't linked to any component")
+            }
+            Placeholder::MissingLinkTarget { component, target } => write!(
+                f,
+                "component {component} links to {target}, which isn't in the manifest"
+            ),
+        }
+    }
+}
+
+/// The error returned when a manifest still contains placeholders or missing links
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StrictError {
+    pub placeholders: Vec<Placeholder>,
+}
+
+impl fmt::Display for StrictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "manifest contains {} placeholder(s) or missing link(s):",
+            self.placeholders.len()
+        )?;
+        for placeholder in &self.placeholders {
+            write!(f, "\n  - {placeholder}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StrictError {}
+
+/// Checks a manifest for placeholder images, floating tags, default names and missing links,
+/// returning every one that was found
+pub fn check_strict(manifest: &Manifest) -> Result<(), StrictError> {
+    let mut placeholders = Vec::new();
+
+    if PLACEHOLDER_NAMES.contains(&manifest.metadata.name.as_str()) {
+        placeholders.push(Placeholder::Name {
+            name: manifest.metadata.name.clone(),
+        });
+    }
+
+    for component in &manifest.spec.components {
+        let image = match &component.properties {
+            Properties::Component { properties } => &properties.image,
+            Properties::Capability { properties } => &properties.image,
+        };
+        if image.starts_with(PLACEHOLDER_IMAGE_PREFIX)
+            || PLACEHOLDER_IMAGES
+                .iter()
+                .any(|placeholder| image.starts_with(placeholder))
+        {
+            placeholders.push(Placeholder::Image {
+                component: component.name.clone(),
+                image: image.clone(),
+            });
+        } else if is_floating(image) {
+            placeholders.push(Placeholder::FloatingTag {
+                component: component.name.clone(),
+                image: image.clone(),
+            });
+        }
+    }
+
+    let links: Vec<(&str, &str)> = manifest
+        .spec
+        .components
+        .iter()
+        .flat_map(|component| {
+            component
+                .traits
+                .iter()
+                .flatten()
+                .filter_map(move |t| match &t.properties {
+                    TraitProperty::Link(link) => {
+                        Some((component.name.as_str(), link.target.as_str()))
+                    }
+                    _ => None,
+                })
+        })
+        .collect();
+    for (source, target) in &links {
+        if !manifest.spec.components.iter().any(|c| c.name == *target) {
+            placeholders.push(Placeholder::MissingLinkTarget {
+                component: source.to_string(),
+                target: target.to_string(),
+            });
+        }
+    }
+    for component in &manifest.spec.components {
+        let linked = links
+            .iter()
+            .any(|(source, target)| *source == component.name || *target == component.name);
+        if matches!(component.properties, Properties::Capability { .. }) && !linked {
+            placeholders.push(Placeholder::UnlinkedProvider {
+                component: component.name.clone(),
+            });
+        }
+    }
+
+    if placeholders.is_empty() {
+        Ok(())
+    } else {
+        Err(StrictError { placeholders })
+    }
+}
+
+/// Returns true if an image reference isn't pinned to a digest and has a floating tag or no tag
+/// at all, which means `latest`. Local `file://` images are never floating
+fn is_floating(image: &str) -> bool {
+    if image.contains('@') || image.starts_with("file://") {
+        return false;
+    }
+    let name = image.rsplit_once('/').map_or(image, |(_, name)| name);
+    match name.split_once(':') {
+        Some((_, tag)) => FLOATING_TAGS.contains(&tag),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use wadm::model::{Manifest, TraitProperty};
+
+    use super::{check_strict, Placeholder};
+    use crate::catalog::ImageChannel;
+    use crate::lockfile::Lockfile;
+    use crate::plan::plan_application;
+    use crate::{plan_to_wadm, GenerateOptions};
+
+    fn manifest(name: &str, image: &str, imports: &[&str], options: &GenerateOptions) -> Manifest {
+        let plan = plan_application(
+            name,
+            image,
+            &imports.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            &["wasi:http/incoming-handler@0.2.0".to_string()],
+            options,
+        );
+        plan_to_wadm(&plan, "A Shop", "v1.0.0")
+    }
+
+    fn pinned_options() -> GenerateOptions {
+        GenerateOptions {
+            provider_images: ImageChannel::Pinned,
+            lockfile: Some(Lockfile {
+                images: BTreeMap::from([
+                    (
+                        "ghcr.io/wasmcloud/keyvalue-redis:0.24.0".to_string(),
+                        "sha256:aaaa".to_string(),
+                    ),
+                    (
+                        "ghcr.io/wasmcloud/http-server:0.20.0".to_string(),
+                        "sha256:bbbb".to_string(),
+                    ),
+                ]),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_strict_passes_pinned_manifest() {
+        let manifest = manifest(
+            "shop",
+            "ghcr.io/acme/shop:1.0.0",
+            &["wasi:keyvalue/store@0.2.0-draft"],
+            &pinned_options(),
+        );
+        assert_eq!(check_strict(&manifest), Ok(()));
+    }
+
+    #[test]
+    fn test_strict_rejects_placeholders() {
+        let manifest = manifest(
+            "wit2wadm",
+            "myregistry.io/wit2wadm:v0.1.0",
+            &["acme:custom/thing@1.0.0"],
+            &pinned_options(),
+        );
+        let err = check_strict(&manifest).unwrap_err();
+        assert_eq!(
+            err.placeholders,
+            vec![
+                Placeholder::Name {
+                    name: "wit2wadm".to_string()
+                },
+                Placeholder::Image {
+                    component: "wit2wadm".to_string(),
+                    image: "myregistry.io/wit2wadm:v0.1.0".to_string(),
+                },
+                Placeholder::Image {
+                    component: "acme:custom-target".to_string(),
+                    image: "REGISTRY-IMAGE/acme-custom-target:1.0.0".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict_rejects_floating_tags() {
+        for channel in [ImageChannel::Canary, ImageChannel::Latest] {
+            let options = GenerateOptions {
+                provider_images: channel,
+                ..Default::default()
+            };
+            let manifest = manifest(
+                "shop",
+                "ghcr.io/acme/shop:1.0.0",
+                &["wasi:keyvalue/store@0.2.0-draft"],
+                &options,
+            );
+            let err = check_strict(&manifest).unwrap_err();
+            assert_eq!(err.placeholders.len(), 2);
+            assert!(err
+                .placeholders
+                .iter()
+                .all(|p| matches!(p, Placeholder::FloatingTag { .. })));
+        }
+
+        // An image without a tag is `latest`
+        let manifest = manifest("shop", "ghcr.io/acme/shop", &[], &pinned_options());
+        assert_eq!(
+            check_strict(&manifest).unwrap_err().placeholders,
+            vec![Placeholder::FloatingTag {
+                component: "shop".to_string(),
+                image: "ghcr.io/acme/shop".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_strict_rejects_missing_links() {
+        let mut manifest = manifest(
+            "shop",
+            "ghcr.io/acme/shop:1.0.0",
+            &["wasi:keyvalue/store@0.2.0-draft"],
+            &pinned_options(),
+        );
+        for component in manifest.spec.components.iter_mut() {
+            let Some(traits) = component.traits.as_mut() else {
+                continue;
+            };
+            if component.name == "shop" {
+                // Drop the component's link to keyvalue
+                traits.retain(|t| !matches!(t.properties, TraitProperty::Link(_)));
+            } else {
+                // Point the HTTP server's link at a component that doesn't exist
+                for t in traits.iter_mut() {
+                    if let TraitProperty::Link(link) = &mut t.properties {
+                        link.target = "shop-v2".to_string();
+                    }
+                }
+            }
+        }
+
+        let err = check_strict(&manifest).unwrap_err();
+        assert_eq!(
+            err.placeholders,
+            vec![
+                Placeholder::MissingLinkTarget {
+                    component: "wasi:http-source".to_string(),
+                    target: "shop-v2".to_string(),
+                },
+                Placeholder::UnlinkedProvider {
+                    component: "wasi:keyvalue-target".to_string(),
+                },
+            ]
+        );
+    }
+}
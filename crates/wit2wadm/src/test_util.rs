@@ -0,0 +1,54 @@
+//! Components built from a small WIT package, shared by the tests and the benchmarks, which
+//! include this file by path
+
+use wit_component::{ComponentEncoder, StringEncoding};
+use wit_parser::{PackageId, Resolve, UnresolvedPackage};
+
+pub const TEST_WIT: &str = r#"
+    package wit2wadm:test;
+
+    interface store {
+      get: func(key: string) -> option<string>;
+    }
+
+    interface handler {
+      handle: func(request: string) -> string;
+    }
+
+    world server {
+      import store;
+      export handler;
+    }
+
+    world client {
+      import handler;
+    }
+"#;
+
+/// [TEST_WIT] resolved, with the id of its package
+pub fn resolve() -> (Resolve, PackageId) {
+    let mut resolve = Resolve::new();
+    let package = resolve
+        .push(UnresolvedPackage::parse("test.wit".as_ref(), TEST_WIT).unwrap())
+        .unwrap();
+    (resolve, package)
+}
+
+/// A core module built for a world, with the world embedded in its custom sections
+pub fn core_module(world: &str) -> Vec<u8> {
+    let (resolve, package) = resolve();
+    let world = resolve.select_world(package, Some(world)).unwrap();
+    let mut module = wit_component::dummy_module(&resolve, world);
+    wit_component::embed_component_metadata(&mut module, &resolve, world, StringEncoding::UTF8)
+        .unwrap();
+    module
+}
+
+/// A component of the `server` world, importing `store` and exporting `handler`
+pub fn component() -> Vec<u8> {
+    ComponentEncoder::default()
+        .module(&core_module("server"))
+        .unwrap()
+        .encode()
+        .unwrap()
+}
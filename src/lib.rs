@@ -1,7 +1,4 @@
 // lib.rs
-#[cfg(target_arch = "wasm32")]
-use anyhow::Context as _;
-
 #[cfg(target_arch = "wasm32")]
 wit_bindgen::generate!();
 
@@ -17,31 +14,71 @@ impl exports::wasmcloud::tools::convert::Guest for Wit2WadmComponent {
         version: String,
         image: String,
     ) -> Result<String, String> {
-        let (resolve, world) = match wit_component::decode(&component) {
-            Ok(wit_component::DecodedWasm::Component(resolve, world)) => (resolve, world),
-            Err(_) | Ok(wit_component::DecodedWasm::WitPackage(..)) => {
-                return Err("Expected a WIT component".to_string());
-            }
+        let artifact = wit2wadm::oci::read_component_bytes(component)
+            .map_err(|e| format!("Unable to read component: {e:?}"))?;
+        let image = match artifact.image {
+            Some(artifact_image) if image.is_empty() => artifact_image,
+            _ => image,
         };
-
-        let world = resolve
-            .worlds
-            .iter()
-            .find_map(|(id, w)| (id == world).then_some(w))
-            .cloned()
-            .context("component world missing")
-            .expect("should be able to find component world");
-
         let (name, description, version, image) =
             resolve_empty_strings(name, description, version, image);
-        let manifest = wit2wadm::wit2wadm(resolve, &world, &name, &description, &version, &image)
-            .expect("should be able to convert to manifest");
+        let plan = wit2wadm::plan_wasm(
+            &artifact.wasm,
+            None,
+            &name,
+            &image,
+            &wit2wadm::GenerateOptions::default(),
+        )
+        .map_err(|e| format!("Unable to convert component: {e:?}"))?;
+        let manifest = wit2wadm::plan_to_wadm(&plan, &description, &version);
+
+        wit2wadm::render::render_manifests(&[manifest], wit2wadm::render::OutputFormat::Yaml)
+            .map_err(|err| format!("Error rendering manifest: {err:?}"))
+    }
 
-        let yaml_result = serde_yaml::to_string(&manifest);
-        match yaml_result {
-            Ok(yaml_string) => Ok(yaml_string),
-            Err(err) => Err(format!("Error serializing to YAML: {err}")),
+    fn component_to_wadm_with_settings(
+        component: Vec<u8>,
+        settings: String,
+    ) -> Result<String, String> {
+        let wit2wadm::settings::Settings {
+            name,
+            description,
+            version,
+            image,
+            world,
+            format,
+            strict,
+            lockfile,
+            config_file,
+            provider_config_file,
+            options,
+        } = wit2wadm::settings::Settings::parse(&settings)
+            .map_err(|e| format!("Unable to parse settings: {e:?}"))?;
+        // The component has no filesystem access, so the files settings point to can't be read
+        if lockfile.is_some() || config_file.is_some() || provider_config_file.is_some() {
+            return Err(
+                "lockfile, config-file and provider-config-file can't be read by the component"
+                    .to_string(),
+            );
         }
+        let artifact = wit2wadm::oci::read_component_bytes(component)
+            .map_err(|e| format!("Unable to read component: {e:?}"))?;
+        let (name, description, version, image) = resolve_empty_strings(
+            name.unwrap_or_default(),
+            description.unwrap_or_default(),
+            version.unwrap_or_default(),
+            image.or(artifact.image).unwrap_or_default(),
+        );
+
+        let plan = wit2wadm::plan_wasm(&artifact.wasm, world.as_deref(), &name, &image, &options)
+            .map_err(|e| format!("Unable to convert component: {e:?}"))?;
+        let manifest = wit2wadm::plan_to_wadm(&plan, &description, &version);
+        if strict.unwrap_or_default() {
+            wit2wadm::strict::check_strict(&manifest).map_err(|e| e.to_string())?;
+        }
+
+        wit2wadm::render::render_manifests(&[manifest], format.unwrap_or_default())
+            .map_err(|err| format!("Error rendering manifest: {err:?}"))
     }
 }
 
@@ -2,8 +2,9 @@
 wit_bindgen::generate!();
 
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use anyhow::Context as _;
 use clap::builder::ValueParser;
 use clap::{Arg, CommandFactory, FromArgMatches};
 use dir_parser::push_dir;
@@ -12,8 +13,10 @@ use exports::wasmcloud::wash::subcommand::{Argument, Guest as SubcommandGuest, M
 use wasi::cli::environment;
 use wasi::filesystem::preopens::get_directories;
 use wasi::filesystem::types::{Descriptor, DescriptorFlags, OpenFlags, PathFlags};
-use wit2wadm::cli::Args;
-use wit2wadm::{raw_component_to_wadm, raw_wit_to_wadm};
+use wit2wadm::catalog::{catalog_table, describe_package};
+use wit2wadm::cli::{Args, CatalogCommand, CheckArgs, Cli, Command, ExplainFormat};
+use wit2wadm::generate::Io;
+use wit_parser::Resolve;
 
 mod dir_parser;
 
@@ -34,7 +37,7 @@ impl RunGuest for Wit2WadmPlugin {
     fn run() -> Result<(), ()> {
         let args = environment::get_arguments();
 
-        let cmd = Args::command();
+        let cmd = Cli::command();
         let matches = match cmd.try_get_matches_from(args) {
             Ok(matches) => matches,
             Err(err) => {
@@ -42,87 +45,166 @@ impl RunGuest for Wit2WadmPlugin {
                 return Err(());
             }
         };
-        let args = match Args::from_arg_matches(&matches) {
-            Ok(args) => args,
+        let cli = match Cli::from_arg_matches(&matches) {
+            Ok(cli) => cli,
             Err(err) => {
                 eprintln!("Error parsing arguments: {}", err);
                 return Err(());
             }
         };
 
-        let name = args.app_name.unwrap_or_else(|| "wit2wadm".to_string());
-        let description = args
-            .app_description
-            .unwrap_or_else(|| "A wasmCloud Application".to_string());
-        let version = args.app_version.unwrap_or_else(|| "v0.1.0".to_string());
-        let app_image = args
-            .app_image
-            .unwrap_or_else(|| format!("myregistry.io/{name}:{version}"));
-        let manifest = match get_dir(&args.wit_folder_or_component) {
-            // Ok mean this was a directory because we found a match. If we didn't, it was a file
-            Ok(dir) => {
-                let world_name = args.world_name.ok_or_else(|| {
-                    eprintln!("world name is required when providing a WIT folder");
-                })?;
-                let wit = push_dir(dir, &args.wit_folder_or_component).map_err(|e| {
-                    eprintln!("Error loading WIT from directory: {}", e);
-                })?;
-                raw_wit_to_wadm(wit, world_name, &name, description, &version, app_image)
-                    .map_err(|e| eprintln!("Unable to convert wit to wadm manifest: {:?}", e))?
+        match cli.into_command() {
+            Command::Generate(args) => generate(args, None),
+            Command::Inspect(mut args) => {
+                args.explain = args.explain.or(Some(ExplainFormat::Table));
+                generate(args, None)
             }
-            Err(_) => {
-                let file = open_file(
-                    args.wit_folder_or_component,
-                    OpenFlags::empty(),
-                    DescriptorFlags::READ,
-                )
-                .map_err(|e| {
-                    eprintln!("Unable to open component file: {}", e);
-                })?;
-                let buf = read_file(file).map_err(|e| {
-                    eprintln!("Unable to read component file: {}", e);
-                })?;
-                raw_component_to_wadm(buf, &name, description, &version, app_image)
-                    .map_err(|e| eprintln!("Unable to convert component to wadm manifest: {}", e))?
+            Command::Check(CheckArgs {
+                manifest,
+                generate: args,
+            }) => generate(args, Some(manifest)),
+            Command::Catalog(CatalogCommand::List) => {
+                println!("{}", catalog_table());
+                Ok(())
             }
-        };
-
-        let yaml_result = serde_yaml::to_string(&manifest);
-        match yaml_result {
-            Ok(yaml_string) => {
-                println!("{}", yaml_string);
+            Command::Catalog(CatalogCommand::Show { package }) => {
+                let description = describe_package(&package)
+                    .ok_or_else(|| eprintln!("The catalog has no providers for {package}"))?;
+                println!("{description}");
                 Ok(())
             }
-            Err(err) => {
-                eprintln!("Error serializing to YAML: {}", err);
+            Command::Lock(_) => {
+                eprintln!("lock is not supported by the wash plugin, use the wit2wadm CLI instead");
                 Err(())
             }
         }
     }
 }
 
+/// Generates a manifest, or checks an existing manifest against what would be generated
+fn generate(args: Args, check: Option<PathBuf>) -> Result<(), ()> {
+    wit2wadm::generate(args, check, &mut WasiIo)
+        .map(|_| ())
+        .map_err(|e| eprintln!("{e:?}"))
+}
+
+/// Reads and writes the directories wash gave the plugin access to. Paths outside of them, like
+/// the default settings file and lockfile when the current directory isn't one, don't exist
+struct WasiIo;
+
+impl Io for WasiIo {
+    fn exists(&self, path: &Path) -> bool {
+        get_dir(path).is_ok() || open_file(path, OpenFlags::empty(), DescriptorFlags::READ).is_ok()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        get_dir(path).is_ok()
+    }
+
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        let file = open_file(path, OpenFlags::empty(), DescriptorFlags::READ)
+            .map_err(anyhow::Error::msg)?;
+        read_file(file).map_err(anyhow::Error::msg)
+    }
+
+    fn read_dir(&self, path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let dir = get_dir(path).map_err(anyhow::Error::msg)?;
+        let entries = dir
+            .read_directory()
+            .map_err(|e| anyhow::anyhow!("Failed to read directory: {}", e))?;
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.read_directory_entry().transpose() {
+            let entry =
+                entry.map_err(|e| anyhow::anyhow!("Failed to read directory entry: {}", e))?;
+            paths.push(path.join(entry.name));
+        }
+        Ok(paths)
+    }
+
+    fn read_stdin(&mut self) -> anyhow::Result<Vec<u8>> {
+        read_stdin().map_err(anyhow::Error::msg)
+    }
+
+    fn read_wit(&self, path: &Path) -> anyhow::Result<Resolve> {
+        let dir = get_dir(path).map_err(anyhow::Error::msg)?;
+        let mut resolve = Resolve::new();
+        for package in push_dir(dir, path).map_err(anyhow::Error::msg)? {
+            resolve
+                .push(package)
+                .context("failed to load wit document")?;
+        }
+        Ok(resolve)
+    }
+
+    fn current_dir(&self) -> anyhow::Result<PathBuf> {
+        environment::initial_cwd()
+            .map(PathBuf::from)
+            .context("a WIT folder or component is required")
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> anyhow::Result<()> {
+        // Output can only be written to directories the plugin was given access to
+        get_dir(path).map(|_| ()).map_err(anyhow::Error::msg)
+    }
+
+    fn write(&mut self, path: &Path, contents: &[u8], overwrite: bool) -> anyhow::Result<()> {
+        let (dir, name) = path
+            .parent()
+            .zip(path.file_name().and_then(|name| name.to_str()))
+            .with_context(|| format!("Invalid output path {}", path.display()))?;
+        let dir = get_dir(dir).map_err(anyhow::Error::msg)?;
+        write_file(&dir, name, contents, overwrite).map_err(anyhow::Error::msg)
+    }
+
+    fn print(&mut self, output: &str) {
+        println!("{output}");
+    }
+
+    fn log(&mut self, message: &str) {
+        eprintln!("{message}");
+    }
+}
+
 // Our plugin's metadata implemented for the subcommand interface
 impl SubcommandGuest for Wit2WadmPlugin {
     fn register() -> Metadata {
-        let cmd = Args::command();
-        let (arguments, flags): (Vec<_>, Vec<_>) =
-            cmd.get_arguments().partition(|arg| arg.is_positional());
-        // There isn't a partition_map function without importing another crate
-        let arguments = arguments
+        let cmd = Cli::command();
+        // wash only knows about flat lists of flags and positional arguments, so the subcommand
+        // is registered as the first positional argument, followed by the positional arguments
+        // of `generate` and then those of the other subcommands, and the flags of every
+        // subcommand are registered together
+        let subcommands = cmd
+            .get_subcommands()
+            .map(|subcommand| subcommand.get_name().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut arguments = vec![(
+            "command".to_string(),
+            Argument {
+                description: format!("The command to run, one of {subcommands}"),
+                is_path: false,
+                required: false,
+            },
+        )];
+        let mut flags: Vec<(String, Argument)> = Vec::new();
+        let top_level = cmd.get_arguments().map(|arg| (arg, Argument::from(arg)));
+        let nested = subcommands_of(&cmd)
             .into_iter()
-            .map(|arg| (arg.get_id().to_string(), Argument::from(arg)))
-            .collect();
-        let flags = flags
-            .into_iter()
-            .map(|arg| {
+            .flat_map(|command| command.get_arguments())
+            .map(|arg| (arg, subcommand_argument(arg)));
+        for (arg, argument) in top_level.chain(nested) {
+            let (registered, name) = if arg.is_positional() {
+                (&mut arguments, arg.get_id().as_str())
+            } else {
                 (
-                    arg.get_long()
-                        .unwrap_or_else(|| arg.get_id().as_str())
-                        .to_string(),
-                    Argument::from(arg),
+                    &mut flags,
+                    arg.get_long().unwrap_or_else(|| arg.get_id().as_str()),
                 )
-            })
-            .collect();
+            };
+            if !registered.iter().any(|(existing, _)| existing == name) {
+                registered.push((name.to_string(), argument));
+            }
+        }
         Metadata {
             name: "Wit2Wadm Wash Plugin".to_string(),
             id: "wit2wadm".to_string(),
@@ -137,14 +219,53 @@ impl SubcommandGuest for Wit2WadmPlugin {
     }
 }
 
+/// Every subcommand, including nested ones like `catalog show`
+fn subcommands_of(cmd: &clap::Command) -> Vec<&clap::Command> {
+    cmd.get_subcommands()
+        .flat_map(|subcommand| std::iter::once(subcommand).chain(subcommands_of(subcommand)))
+        .collect()
+}
+
+/// Registers an argument that only a subcommand takes. wash requires required arguments for
+/// every invocation of the plugin, so it's never registered as required
+fn subcommand_argument(arg: &Arg) -> Argument {
+    Argument {
+        required: false,
+        ..Argument::from(arg)
+    }
+}
+
+/// Opens a directory wash gave the plugin access to, or one nested inside it, like the blobs of
+/// an OCI image layout
 fn get_dir(path: impl AsRef<Path>) -> Result<Descriptor, String> {
-    get_directories()
+    let path = path.as_ref();
+    let not_found = || format!("Could not find directory {}", path.display());
+    let (dir, relative) = get_directories()
         .into_iter()
-        .find_map(|(dir, dir_path)| {
-            (<std::string::String as std::convert::AsRef<Path>>::as_ref(&dir_path) == path.as_ref())
-                .then_some(dir)
+        .filter_map(|(dir, dir_path)| {
+            let relative = path.strip_prefix(&dir_path).ok()?.to_path_buf();
+            Some((dir, dir_path.len(), relative))
         })
-        .ok_or_else(|| format!("Could not find directory {}", path.as_ref().display()))
+        .max_by_key(|(_, len, _)| *len)
+        .map(|(dir, _, relative)| (dir, relative))
+        .ok_or_else(not_found)?;
+    if relative.as_os_str().is_empty() {
+        return Ok(dir);
+    }
+    let relative = relative
+        .to_str()
+        .ok_or_else(|| "Path is not a valid string".to_string())?;
+    // Directories under a read-only preopen can't be opened for writing
+    [
+        DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        DescriptorFlags::READ,
+    ]
+    .into_iter()
+    .find_map(|flags| {
+        dir.open_at(PathFlags::empty(), relative, OpenFlags::DIRECTORY, flags)
+            .ok()
+    })
+    .ok_or_else(not_found)
 }
 
 /// Opens the given file. This should be the canonicalized path to the file.
@@ -177,6 +298,47 @@ pub(crate) fn open_file(
     .map_err(|e| format!("Failed to open file {}: {}", path.as_ref().display(), e))
 }
 
+/// Writes a file in a directory atomically, by writing to a temporary file and renaming it into
+/// place. An existing file is only replaced when `overwrite` is set
+fn write_file(
+    dir: &Descriptor,
+    name: &str,
+    contents: &[u8],
+    overwrite: bool,
+) -> Result<(), String> {
+    if !overwrite && dir.stat_at(PathFlags::empty(), name).is_ok() {
+        return Err("file already exists, pass --force to overwrite it".to_string());
+    }
+    let temp = format!(".{name}.tmp");
+    let written = write_new_file(dir, &temp, contents)
+        .and_then(|_| dir.rename_at(&temp, dir, name).map_err(|e| e.to_string()));
+    if written.is_err() {
+        let _ = dir.unlink_file_at(&temp);
+    }
+    written
+}
+
+fn write_new_file(dir: &Descriptor, name: &str, contents: &[u8]) -> Result<(), String> {
+    let file = dir
+        .open_at(
+            PathFlags::empty(),
+            name,
+            OpenFlags::CREATE | OpenFlags::TRUNCATE,
+            DescriptorFlags::WRITE,
+        )
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+    let stream = file
+        .write_via_stream(0)
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    // Streams accept at most 4096 bytes per blocking write
+    for chunk in contents.chunks(4096) {
+        stream
+            .blocking_write_and_flush(chunk)
+            .map_err(|e| format!("Failed to write file: {:?}", e))?;
+    }
+    Ok(())
+}
+
 fn read_file(dir: Descriptor) -> Result<Vec<u8>, String> {
     let mut body = dir
         .read_via_stream(0)
@@ -188,6 +350,15 @@ fn read_file(dir: Descriptor) -> Result<Vec<u8>, String> {
     Ok(buf)
 }
 
+fn read_stdin() -> Result<Vec<u8>, String> {
+    let mut stdin = wasi::cli::stdin::get_stdin();
+    let mut buf = vec![];
+    InputStreamReader::from(&mut stdin)
+        .read_to_end(&mut buf)
+        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+    Ok(buf)
+}
+
 // Helpers for reading from streams.
 pub struct InputStreamReader<'a> {
     stream: &'a mut crate::wasi::io::streams::InputStream,
@@ -229,3 +400,54 @@ impl std::io::Read for InputStreamReader<'_> {
 }
 
 export!(Wit2WadmPlugin);
+
+#[cfg(test)]
+mod test {
+    use super::exports::wasmcloud::wash::subcommand::Guest as _;
+    use super::Wit2WadmPlugin;
+
+    #[test]
+    fn test_register() {
+        let metadata = Wit2WadmPlugin::register();
+        let arguments: Vec<&str> = metadata
+            .arguments
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            arguments,
+            [
+                "command",
+                "wit_folder_or_component",
+                "world_name",
+                "package",
+                "layouts"
+            ]
+        );
+
+        // Nothing is required, or wash would ask for `--manifest` when running `generate`
+        assert!(metadata.arguments.iter().all(|(_, arg)| !arg.required));
+        assert!(metadata.flags.iter().all(|(_, arg)| !arg.required));
+
+        let flag = |name: &str| {
+            metadata
+                .flags
+                .iter()
+                .find_map(|(flag, arg)| (flag == name).then_some(arg))
+                .unwrap_or_else(|| panic!("{name} should be registered"))
+        };
+        assert!(flag("manifest").is_path);
+        assert!(flag("lockfile").is_path);
+        assert!(!flag("name").is_path);
+        flag("strict");
+        flag("batch");
+        assert_eq!(
+            metadata
+                .flags
+                .iter()
+                .filter(|(flag, _)| flag == "lockfile")
+                .count(),
+            1
+        );
+    }
+}